@@ -0,0 +1,34 @@
+// Scans generated sources of growing size, the throughput should stay about
+// the same for every size if scanning is linear
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rust_interpreter::scanner::Scanner;
+
+// A chunk of Lox with non-ASCII text in the strings and comments, repeated
+// until the source is at least `size` bytes
+fn source(size: usize) -> String {
+    let chunk = "\
+// Grüße, 世界 🌍
+fun greet(name) {
+  var greeting = \"héllo, \" + name + \" ✓\";
+  if (greeting != nil and name != \"\") print greeting;
+  return 3.14 * 2 >= 6;
+}
+";
+    chunk.repeat(size / chunk.len() + 1)
+}
+
+fn scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+    for size in [64 * 1024, 256 * 1024, 1024 * 1024, 4 * 1024 * 1024] {
+        let source = source(size);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &source, |b, source| {
+            b.iter(|| Scanner::new(black_box(source.clone())).scan_all())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
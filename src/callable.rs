@@ -0,0 +1,136 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    environment::Environment, error::LoxErrors, interpreter::Interpreter, literal::LiteralValue,
+    stmt::Stmt, token::Token, token_type::TokenType, value::Value,
+};
+
+// Anything that can be called from Lox code
+pub trait LoxCallable: fmt::Display {
+    fn arity(&self) -> usize;
+    // Takes the Rc so a class can hand a reference to itself to the new instance
+    fn call(self: Rc<Self>, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, LoxErrors>;
+}
+
+// A user defined function together with the environment it was declared in
+pub struct LoxFunction {
+    name: Token,
+    params: Vec<Token>,
+    body: Rc<Vec<Stmt>>,
+    closure: Rc<RefCell<Environment>>,
+    // `init` methods always return `this`
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        name: Token,
+        params: Vec<Token>,
+        body: Rc<Vec<Stmt>>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            body,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Creates a copy of the method whose closure has `this` bound to the instance
+    pub fn bind(&self, instance: Value) -> LoxFunction {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        environment.define("this".to_string(), instance);
+
+        LoxFunction::new(
+            self.name.clone(),
+            self.params.clone(),
+            Rc::clone(&self.body),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+
+    // The instance a bound method was bound to
+    fn this(&self) -> Result<Value, LoxErrors> {
+        let this = Token::new(
+            TokenType::THIS,
+            "this".to_string(),
+            LiteralValue::Null,
+            self.name.line,
+            self.name.column,
+            self.name.span,
+        );
+        self.closure.borrow().get_at(0, &this)
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    fn call(self: Rc<Self>, interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, LoxErrors> {
+        // Every call gets its own environment so recursion works
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        for (param, argument) in self.params.iter().zip(arguments) {
+            environment.define(param.lexeme.clone(), argument);
+        }
+
+        match interpreter.execute_block(&self.body, Rc::new(RefCell::new(environment))) {
+            Ok(()) | Err(LoxErrors::RETURN(_)) if self.is_initializer => self.this(),
+            Ok(()) => Ok(Value::Nil),
+            Err(LoxErrors::RETURN(value)) => Ok(value),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name.lexeme)
+    }
+}
+
+// A function implemented in Rust and exposed to Lox code
+pub struct NativeFunction {
+    arity: usize,
+    function: fn(&[Value]) -> Value,
+}
+
+impl NativeFunction {
+    pub fn new(arity: usize, function: fn(&[Value]) -> Value) -> Self {
+        Self { arity, function }
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(self: Rc<Self>, _interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, LoxErrors> {
+        Ok((self.function)(&arguments))
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+/// Seconds since the unix epoch, mostly useful for benchmarking Lox code
+pub fn clock(_arguments: &[Value]) -> Value {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Value::Number(now.as_secs_f64())
+}
@@ -0,0 +1,199 @@
+use std::{
+    fs,
+    io::{self, Read},
+};
+
+use crate::{diagnostic::ErrorFormat, trace::Channel};
+
+// Exit codes from sysexits.h, same as jlox uses
+pub const EXIT_USAGE: i32 = 64;
+pub const EXIT_NO_INPUT: i32 = 66;
+
+pub const USAGE: &str = "\
+Usage: lox [command] [file]
+
+Commands:
+  run <file>       Run a script
+  tokens <file>    Print the tokens the scanner produces
+  ast <file>       Print the syntax tree the parser produces
+  check <file>     Scan, parse and resolve a script without running it
+  repl             Start the interactive prompt (the default)
+
+Pass `-` as the file to read the script from stdin.
+
+Options:
+  -h, --help       Print this message
+  --trace-scanner  Log every token the scanner produces to stderr
+  --trace-parser   Log every token the parser consumes and statement it builds
+  --trace-exec     Log every statement executed and function called
+  --error-format=human|json
+                   How errors are printed, json gives one object per line
+
+The LOX_LOG environment variable takes a comma separated list of the same
+channels (scanner, parser, exec or all).";
+
+// Everything that was given on the command line
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    // Trace channels switched on with the `--trace-*` flags
+    pub trace: Vec<Channel>,
+    pub error_format: ErrorFormat,
+}
+
+// What the user asked for on the command line
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Input),
+    Tokens(Input),
+    Ast(Input),
+    Check(Input),
+    Repl,
+    Help,
+}
+
+// Where the source of a script comes from
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Stdin,
+    File(String),
+}
+
+impl Input {
+    pub fn read(&self) -> io::Result<String> {
+        match self {
+            Input::Stdin => {
+                let mut source = String::new();
+                io::stdin().read_to_string(&mut source)?;
+                Ok(source)
+            }
+            Input::File(path) => fs::read_to_string(path),
+        }
+    }
+
+    /// How the input is named in error messages
+    pub fn name(&self) -> &str {
+        match self {
+            Input::Stdin => "<stdin>",
+            Input::File(path) => path,
+        }
+    }
+}
+
+/// Parses the arguments without the program name, the error is the message
+/// to show above the usage
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    // The trace and error format flags can go anywhere, the rest is positional
+    let mut trace = Vec::new();
+    let mut error_format = ErrorFormat::default();
+    let mut positional = Vec::new();
+    for arg in args {
+        if let Some(name) = arg.strip_prefix("--trace-") {
+            match Channel::from_name(name) {
+                Some(channel) => trace.push(channel),
+                None => return Err(format!("Unknown option '{}'.", arg)),
+            }
+        } else if let Some(name) = arg.strip_prefix("--error-format=") {
+            match ErrorFormat::from_name(name) {
+                Some(format) => error_format = format,
+                None => return Err(format!("Unknown error format '{}'.", name)),
+            }
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+
+    let command = parse_command(&positional)?;
+    Ok(Options {
+        command,
+        trace,
+        error_format,
+    })
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(Command::Help);
+    }
+
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(Command::Repl);
+    };
+
+    let command: fn(Input) -> Command = match command.as_str() {
+        "run" => Command::Run,
+        "tokens" => Command::Tokens,
+        "ast" => Command::Ast,
+        "check" => Command::Check,
+        "repl" => {
+            return match rest {
+                [] => Ok(Command::Repl),
+                [extra, ..] => Err(format!("Unexpected argument '{}'.", extra)),
+            };
+        }
+        other => return Err(format!("Unknown command '{}'.", other)),
+    };
+
+    match rest {
+        [] => Err("Missing the file to read.".to_string()),
+        [path] if path == "-" => Ok(command(Input::Stdin)),
+        [path] if path.starts_with('-') => Err(format!("Unknown option '{}'.", path)),
+        [path] => Ok(command(Input::File(path.clone()))),
+        [_, extra, ..] => Err(format!("Unexpected argument '{}'.", extra)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_args, Command, Input, Options};
+    use crate::{diagnostic::ErrorFormat, trace::Channel};
+
+    fn parse_options(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_options(args).map(|options| options.command)
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(parse(&[]), Ok(Command::Repl));
+        assert_eq!(parse(&["repl"]), Ok(Command::Repl));
+        assert_eq!(parse(&["run", "a.lox"]), Ok(Command::Run(Input::File("a.lox".to_string()))));
+        assert_eq!(parse(&["tokens", "-"]), Ok(Command::Tokens(Input::Stdin)));
+        assert_eq!(parse(&["ast", "a.lox"]), Ok(Command::Ast(Input::File("a.lox".to_string()))));
+        assert_eq!(parse(&["check", "-"]), Ok(Command::Check(Input::Stdin)));
+        assert_eq!(parse(&["run", "a.lox", "--help"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_usage_errors() {
+        assert!(parse(&["run"]).is_err());
+        assert!(parse(&["run", "a.lox", "b.lox"]).is_err());
+        assert!(parse(&["repl", "a.lox"]).is_err());
+        assert!(parse(&["-p", "a.lox"]).is_err());
+        assert!(parse(&["run", "--verbose"]).is_err());
+        assert!(parse(&["run", "a.lox", "--trace-everything"]).is_err());
+    }
+
+    #[test]
+    fn test_trace_flags() {
+        let options = parse_options(&["--trace-exec", "run", "a.lox", "--trace-scanner"]).unwrap();
+        assert_eq!(options.command, Command::Run(Input::File("a.lox".to_string())));
+        assert_eq!(options.trace, vec![Channel::EXEC, Channel::SCANNER]);
+
+        assert_eq!(parse_options(&["repl"]).unwrap().trace, vec![]);
+    }
+
+    #[test]
+    fn test_error_format() {
+        assert_eq!(parse_options(&["check", "-"]).unwrap().error_format, ErrorFormat::Human);
+        assert_eq!(
+            parse_options(&["check", "--error-format=json", "-"]).unwrap().error_format,
+            ErrorFormat::Json
+        );
+        assert!(parse(&["check", "--error-format=xml", "-"]).is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    cell::RefCell,
+    env,
+    fmt::Write,
+    io::{self, IsTerminal},
+};
+
+use crate::token::{Span, Token};
+
+// How diagnostics are written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    // rustc style with the source line and carets, colored on a terminal
+    #[default]
+    Human,
+    // One JSON object per line, for editors and other tools
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_name(name: &str) -> Option<ErrorFormat> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// Points at a piece of the source, `message` is printed next to the underline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub line: usize,
+    pub column: usize,
+    // Not known for errors that only have a line and column, one character
+    // gets underlined then
+    pub span: Option<Span>,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(token: &Token, message: &str) -> Self {
+        Self {
+            line: token.line,
+            column: token.column,
+            span: Some(token.span),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn at(line: usize, column: usize, message: &str) -> Self {
+        Self {
+            line,
+            column,
+            span: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+// An error message together with the places in the source it is about
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    // Where the error is, errors without a position only print the message
+    pub primary: Option<Label>,
+    // Related places, like the opening paren of an unclosed group
+    pub secondary: Vec<Label>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            primary: None,
+            secondary: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    /// An error that points at the token
+    pub fn from_token(message: &str, token: &Token) -> Self {
+        Self {
+            primary: Some(Label::new(token, "")),
+            ..Self::new(message)
+        }
+    }
+
+    /// An error that points at a single character
+    pub fn at(message: &str, line: usize, column: usize) -> Self {
+        Self {
+            primary: Some(Label::at(line, column, "")),
+            ..Self::new(message)
+        }
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.secondary.push(label);
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help.push(help.to_string());
+        self
+    }
+
+    /// Renders the diagnostic like rustc does, the source lines are only shown
+    /// when the source is known
+    pub fn render(&self, file: &str, source: Option<&str>, color: bool) -> String {
+        let style = Style { color };
+        let mut out = String::new();
+
+        let _ = writeln!(out, "{}{}", style.error("error"), style.bold(&format!(": {}", self.message)));
+
+        let Some(primary) = &self.primary else {
+            for help in &self.help {
+                let _ = writeln!(out, "  = {}: {}", style.bold("help"), help);
+            }
+            return out;
+        };
+
+        let mut labels: Vec<(&Label, bool)> = vec![(primary, true)];
+        labels.extend(self.secondary.iter().map(|label| (label, false)));
+        labels.sort_by_key(|(label, _)| (label.line, label.column));
+
+        let width = labels.iter().map(|(label, _)| label.line.to_string().len()).max().unwrap_or(1);
+        let gutter = " ".repeat(width);
+
+        let _ = writeln!(out, "{}{} {}:{}:{}", gutter, style.blue("-->"), file, primary.line, primary.column);
+
+        if let Some(source) = source {
+            let _ = writeln!(out, "{} {}", gutter, style.blue("|"));
+
+            let mut printed_line = None;
+            for (label, is_primary) in labels {
+                // Splitting on '\n' keeps the empty last line the end of the file can be on
+                let Some(text) = source.split('\n').nth(label.line - 1) else {
+                    continue;
+                };
+                let text = text.strip_suffix('\r').unwrap_or(text);
+
+                if printed_line != Some(label.line) {
+                    let number = format!("{:>width$}", label.line, width = width);
+                    let _ = writeln!(out, "{} {} {}", style.blue(&number), style.blue("|"), text);
+                    printed_line = Some(label.line);
+                }
+
+                let underline = underline(text, label, if is_primary { '^' } else { '-' });
+                let underline = if is_primary {
+                    style.error(&underline)
+                } else {
+                    style.blue(&underline)
+                };
+                let message = if label.message.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", label.message)
+                };
+                let _ = writeln!(out, "{} {} {}{}", gutter, style.blue("|"), underline, message);
+            }
+        }
+
+        for help in &self.help {
+            let _ = writeln!(out, "{} {} {}: {}", gutter, style.blue("="), style.bold("help"), help);
+        }
+
+        out
+    }
+
+    // Works out the spans of the labels that only know their line and column
+    fn with_spans(&self, source: &str) -> Diagnostic {
+        let mut diagnostic = self.clone();
+        for label in diagnostic.primary.iter_mut().chain(diagnostic.secondary.iter_mut()) {
+            if label.span.is_some() {
+                continue;
+            }
+
+            let line_start: usize = source
+                .split_inclusive('\n')
+                .take(label.line - 1)
+                .map(str::len)
+                .sum();
+            let start = line_start + line_offset(&source[line_start..], label.column.max(1) - 1);
+            let end = source[start..].chars().next().map_or(start, |c| start + c.len_utf8());
+            label.span = Some(Span::new(start, end));
+        }
+        diagnostic
+    }
+
+    /// Renders the diagnostic as a single line of JSON
+    pub fn render_json(&self, file: &str) -> String {
+        let mut out = format!(
+            "{{\"severity\":\"error\",\"message\":{},\"file\":{}",
+            json_string(&self.message),
+            json_string(file)
+        );
+
+        match &self.primary {
+            Some(primary) => {
+                let _ = write!(
+                    out,
+                    ",\"line\":{},\"column\":{},\"span\":{},\"label\":{}",
+                    primary.line,
+                    primary.column,
+                    json_span(primary.span),
+                    json_string(&primary.message)
+                );
+            }
+            None => out.push_str(",\"line\":null,\"column\":null,\"span\":null,\"label\":null"),
+        }
+
+        let labels: Vec<String> = self
+            .secondary
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"message\":{},\"line\":{},\"column\":{},\"span\":{}}}",
+                    json_string(&label.message),
+                    label.line,
+                    label.column,
+                    json_span(label.span)
+                )
+            })
+            .collect();
+        let help: Vec<String> = self.help.iter().map(|help| json_string(help)).collect();
+
+        let _ = write!(out, ",\"labels\":[{}],\"help\":[{}]}}", labels.join(","), help.join(","));
+        out
+    }
+}
+
+// The markers under the label, lined up with the text above it. Tabs are kept
+// so the markers still line up when the terminal expands them.
+fn underline(text: &str, label: &Label, marker: char) -> String {
+    let column = label.column.max(1) - 1;
+    let mut line: String = text
+        .chars()
+        .take(column)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    // Only as far as the end of the line for tokens that span lines
+    let length = match label.span {
+        Some(span) => text[line_offset(text, column)..]
+            .char_indices()
+            .take_while(|(index, _)| *index < span.end - span.start)
+            .count(),
+        None => 1,
+    };
+
+    line.extend(std::iter::repeat_n(marker, length.max(1)));
+    line
+}
+
+// Byte offset of the character at `column` (counted from 0)
+fn line_offset(text: &str, column: usize) -> usize {
+    text.char_indices().nth(column).map_or(text.len(), |(index, _)| index)
+}
+
+fn json_span(span: Option<Span>) -> String {
+    match span {
+        Some(span) => format!("{{\"start\":{},\"end\":{}}}", span.start, span.end),
+        None => "null".to_string(),
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// ANSI colors, everything is passed through untouched when color is off
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn error(&self, text: &str) -> String {
+        self.paint("1;31", text)
+    }
+
+    fn blue(&self, text: &str) -> String {
+        self.paint("1;34", text)
+    }
+
+    fn bold(&self, text: &str) -> String {
+        self.paint("1", text)
+    }
+}
+
+// What every diagnostic is rendered against, set up by the CLI and the REPL
+// before a piece of source gets run
+#[derive(Default)]
+struct Emitter {
+    format: ErrorFormat,
+    file: String,
+    source: Option<String>,
+}
+
+thread_local! {
+    static EMITTER: RefCell<Emitter> = RefCell::new(Emitter::default());
+}
+
+pub fn set_format(format: ErrorFormat) {
+    EMITTER.with(|emitter| emitter.borrow_mut().format = format);
+}
+
+/// The source the following diagnostics point into, `file` is the name shown
+pub fn set_source(file: &str, source: &str) {
+    EMITTER.with(|emitter| {
+        let mut emitter = emitter.borrow_mut();
+        emitter.file = file.to_string();
+        emitter.source = Some(source.to_string());
+    });
+}
+
+/// Writes the diagnostic to stderr in the chosen format
+pub fn emit(diagnostic: &Diagnostic) {
+    EMITTER.with(|emitter| {
+        let emitter = emitter.borrow();
+        let file = if emitter.file.is_empty() { "<input>" } else { &emitter.file };
+
+        let resolved;
+        let diagnostic = match &emitter.source {
+            Some(source) => {
+                resolved = diagnostic.with_spans(source);
+                &resolved
+            }
+            None => diagnostic,
+        };
+
+        match emitter.format {
+            ErrorFormat::Human => {
+                let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+                eprint!("{}", diagnostic.render(file, emitter.source.as_deref(), color));
+            }
+            ErrorFormat::Json => eprintln!("{}", diagnostic.render_json(file)),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Label};
+    use crate::{literal::LiteralValue, token::{Span, Token}, token_type::TokenType};
+
+    fn token(line: usize, column: usize, start: usize, lexeme: &str) -> Token {
+        Token::new(
+            TokenType::IDENTIFIER,
+            lexeme.to_string(),
+            LiteralValue::Null,
+            line,
+            column,
+            Span::new(start, start + lexeme.len()),
+        )
+    }
+
+    #[test]
+    fn test_render() {
+        let source = "print (1 +\n  two;";
+        let diagnostic = Diagnostic::from_token("Expect ')' after expression.", &token(2, 6, 16, ";"))
+            .with_label(Label::new(&token(1, 7, 6, "("), "unclosed '(' opened here"))
+            .with_help("add the missing ')'");
+
+        assert_eq!(
+            diagnostic.render("main.lox", Some(source), false),
+            "\
+error: Expect ')' after expression.
+ --> main.lox:2:6
+  |
+1 | print (1 +
+  |       - unclosed '(' opened here
+2 |   two;
+  |      ^
+  = help: add the missing ')'
+"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let diagnostic = Diagnostic::at("Unexpected character '\"'.", 3, 4)
+            .with_label(Label::new(&token(1, 1, 0, "ab"), "here"));
+
+        assert_eq!(
+            diagnostic.render_json("a.lox"),
+            "{\"severity\":\"error\",\"message\":\"Unexpected character '\\\"'.\",\"file\":\"a.lox\",\
+             \"line\":3,\"column\":4,\"span\":null,\"label\":\"\",\
+             \"labels\":[{\"message\":\"here\",\"line\":1,\"column\":1,\"span\":{\"start\":0,\"end\":2}}],\"help\":[]}"
+        );
+
+        let mut diagnostic = Diagnostic::from_token("Expect ';' after value.", &token(1, 8, 7, ""));
+        diagnostic.primary.as_mut().unwrap().message = "at end".to_string();
+        assert_eq!(
+            diagnostic.render_json("-"),
+            "{\"severity\":\"error\",\"message\":\"Expect ';' after value.\",\"file\":\"-\",\
+             \"line\":1,\"column\":8,\"span\":{\"start\":7,\"end\":7},\"label\":\"at end\",\
+             \"labels\":[],\"help\":[]}"
+        );
+        assert!(Diagnostic::new("No input.").render_json("-").contains("\"span\":null,\"label\":null,"));
+    }
+}
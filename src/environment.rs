@@ -0,0 +1,151 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{error::LoxErrors, token::Token, value::Value};
+
+// Storage for the variables, every scope gets its own environment which
+// points to the environment of the scope surrounding it
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// Binds a new variable, redefining an existing one is allowed
+    pub fn define(&mut self, name: String, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, LoxErrors> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    /// Names of the variables defined directly in this environment
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.values.keys()
+    }
+
+    /// Reads a variable from the environment `distance` hops up the chain, the
+    /// resolver has already made sure it is there
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, LoxErrors> {
+        if distance == 0 {
+            return self.values.get(&name.lexeme).cloned().ok_or_else(|| undefined_variable(name));
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get_at(distance - 1, name),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Value) -> Result<(), LoxErrors> {
+        if distance == 0 {
+            self.values.insert(name.lexeme.clone(), value);
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    /// Assigns to an existing variable, unlike `define` this never creates one
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), LoxErrors> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+}
+
+fn undefined_variable(name: &Token) -> LoxErrors {
+    LoxErrors::RUNTIMEERROR(
+        name.clone(),
+        format!("Undefined variable '{}'.", name.lexeme),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::Environment;
+    use crate::{literal::LiteralValue, token::{Span, Token}, token_type::TokenType, value::Value};
+
+    fn identifier(name: &str) -> Token {
+        Token::new(TokenType::IDENTIFIER, name.to_string(), LiteralValue::Null, 1, 1, Span::default())
+    }
+
+    #[test]
+    fn test_define_and_assign() {
+        let mut environment = Environment::new();
+        environment.define("a".to_string(), Value::Number(1.));
+        assert_eq!(environment.get(&identifier("a")).unwrap(), Value::Number(1.));
+
+        environment.assign(&identifier("a"), Value::Number(2.)).unwrap();
+        assert_eq!(environment.get(&identifier("a")).unwrap(), Value::Number(2.));
+    }
+
+    #[test]
+    fn test_lookup_walks_enclosing_scopes() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a".to_string(), Value::Number(1.));
+
+        let mut local = Environment::with_enclosing(Rc::clone(&globals));
+        assert_eq!(local.get(&identifier("a")).unwrap(), Value::Number(1.));
+
+        local.assign(&identifier("a"), Value::Number(2.)).unwrap();
+        assert_eq!(globals.borrow().get(&identifier("a")).unwrap(), Value::Number(2.));
+
+        // Shadowing only touches the inner scope
+        local.define("a".to_string(), Value::Number(3.));
+        assert_eq!(local.get(&identifier("a")).unwrap(), Value::Number(3.));
+        assert_eq!(globals.borrow().get(&identifier("a")).unwrap(), Value::Number(2.));
+    }
+
+    #[test]
+    fn test_access_at_distance() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a".to_string(), Value::Number(1.));
+        let mut local = Environment::with_enclosing(Rc::clone(&globals));
+        local.define("a".to_string(), Value::Number(2.));
+
+        assert_eq!(local.get_at(0, &identifier("a")).unwrap(), Value::Number(2.));
+        assert_eq!(local.get_at(1, &identifier("a")).unwrap(), Value::Number(1.));
+
+        local.assign_at(1, &identifier("a"), Value::Number(3.)).unwrap();
+        assert_eq!(globals.borrow().get(&identifier("a")).unwrap(), Value::Number(3.));
+        assert_eq!(local.get_at(0, &identifier("a")).unwrap(), Value::Number(2.));
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let mut environment = Environment::new();
+        assert!(environment.get(&identifier("x")).is_err());
+        assert!(environment.assign(&identifier("x"), Value::Nil).is_err());
+    }
+}
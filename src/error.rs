@@ -1,44 +1,133 @@
 // Make something from here https://craftinginterpreters.com/scanning.html#error-handling
 
-use crate::{token::Token, token_type::TokenType};
+use crate::{
+    diagnostic::{emit, Diagnostic},
+    token::Token,
+    token_type::TokenType,
+    value::Value,
+};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 pub enum LoxErrors {
-    INVALIDCHARCTER(String),
-    UNTERMINATEDSTRING(),
+    // The character, then the line and column it is at
+    INVALIDCHARCTER(String, usize, usize),
+    // Line and column of the opening quote
+    UNTERMINATEDSTRING(usize, usize),
+    // Line and column of the outermost `/*`
+    UNTERMINATEDCOMMENT(usize, usize),
+    // The escape as written, then the line and column of its `\`
+    INVALIDESCAPE(String, usize, usize),
+    // The number as written, what is wrong with it, then its line and column
+    MALFORMEDNUMBER(String, String, usize, usize),
+    #[allow(dead_code)]
     CANNOTFINDSUBSTRING(String),
     UNEXPECTEDTOKENTYPEFOUND(TokenType),
+    #[allow(dead_code)]
     PRIMARYEXPRERROR(Token),
     PARSEERROR(Token),
+    RESOLVEERROR(Token),
+    RUNTIMEERROR(Token, String),
+    // Not an actual error, used to unwind the interpreter out of a function
+    // body when a return statement is executed
+    RETURN(Value),
 }
 
 impl std::fmt::Display for LoxErrors {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LoxErrors::INVALIDCHARCTER(char) => write!(f, "Invalid character found while parsing: {}", char),
-            LoxErrors::UNTERMINATEDSTRING() => write!(f, "String not terminated"),
+            LoxErrors::INVALIDCHARCTER(char, line, column) => {
+                write!(f, "[line {}, column {}] Error: Unexpected character '{}'.", line, column, char)
+            }
+            LoxErrors::UNTERMINATEDSTRING(line, column) => {
+                write!(f, "[line {}, column {}] Error: Unterminated string.", line, column)
+            }
+            LoxErrors::UNTERMINATEDCOMMENT(line, column) => {
+                write!(f, "[line {}, column {}] Error: Unterminated block comment.", line, column)
+            }
+            LoxErrors::INVALIDESCAPE(escape, line, column) => {
+                write!(f, "[line {}, column {}] Error: Invalid escape sequence '{}'.", line, column, escape)
+            }
+            LoxErrors::MALFORMEDNUMBER(number, reason, line, column) => {
+                write!(f, "[line {}, column {}] Error: Malformed number '{}', {}.", line, column, number, reason)
+            }
             LoxErrors::CANNOTFINDSUBSTRING(string) => write!(f, "Cannot find the specified substring from the string: {}", string),
             LoxErrors::UNEXPECTEDTOKENTYPEFOUND(token_type) => write!(f, "Unexpected token found: {:?}", token_type),
             LoxErrors::PRIMARYEXPRERROR(token) => write!(f, "Unexpected token found while parsing primary expr: {:?}", token),
             LoxErrors::PARSEERROR(token) => write!(f, "Error while parsing : {:?}", token),
+            LoxErrors::RESOLVEERROR(token) => write!(f, "Error while resolving : {:?}", token),
+            LoxErrors::RUNTIMEERROR(token, message) => write!(f, "{}\n[line {}]", message, token.line),
+            LoxErrors::RETURN(_) => write!(f, "Can't return from top-level code."),
         }
     }
 }
 
+impl LoxErrors {
+    /// Exit code for the CLI, following sysexits.h: 70 (EX_SOFTWARE) for runtime
+    /// errors and 65 (EX_DATAERR) for errors in the source itself
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxErrors::RUNTIMEERROR(..) => 70,
+            _ => 65,
+        }
+    }
+}
 
-pub fn report(line: i32, where_: &str, message: &str) {
-    println!("[line {}] Error{}: {}", line, where_, message);
+/// Reports an error that made it up to the CLI, scanner, parser and resolver
+/// errors have already been reported as soon as they were found
+pub fn report_error(err: &LoxErrors) {
+    match err {
+        LoxErrors::INVALIDCHARCTER(..)
+        | LoxErrors::UNTERMINATEDSTRING(..)
+        | LoxErrors::UNTERMINATEDCOMMENT(..)
+        | LoxErrors::INVALIDESCAPE(..)
+        | LoxErrors::MALFORMEDNUMBER(..)
+        | LoxErrors::PARSEERROR(_)
+        | LoxErrors::RESOLVEERROR(_) => {}
+        LoxErrors::RUNTIMEERROR(token, message) => emit(&Diagnostic::from_token(message, token)),
+        _ => emit(&Diagnostic::new(&err.to_string())),
+    }
 }
 
-pub fn error(line: i32, message: &str) {
-    report(line, "", message);
+/// A scanner error, these only know their line and column
+pub fn scanner_error(err: &LoxErrors) {
+    let diagnostic = match err {
+        LoxErrors::INVALIDCHARCTER(char, line, column) => {
+            Diagnostic::at(&format!("Unexpected character '{}'.", char), *line, *column)
+        }
+        LoxErrors::UNTERMINATEDSTRING(line, column) => {
+            Diagnostic::at("Unterminated string.", *line, *column)
+                .with_help("strings end with a closing '\"', add one where this string should end")
+        }
+        LoxErrors::UNTERMINATEDCOMMENT(line, column) => {
+            Diagnostic::at("Unterminated block comment.", *line, *column)
+                .with_help("block comments end with '*/', nested ones need a '*/' each")
+        }
+        LoxErrors::INVALIDESCAPE(escape, line, column) => {
+            Diagnostic::at(&format!("Invalid escape sequence '{}'.", escape), *line, *column)
+                .with_help("the escapes are \\n \\t \\r \\\\ \\\" \\$ \\0 and \\u{...} with 1 to 6 hex digits")
+        }
+        LoxErrors::MALFORMEDNUMBER(number, reason, line, column) => {
+            Diagnostic::at(&format!("Malformed number '{}'.", number), *line, *column).with_help(reason)
+        }
+        _ => Diagnostic::new(&err.to_string()),
+    };
+    emit(&diagnostic);
 }
 
 /// A parser error 
 pub fn parser_error(token: &Token, message: &str) {
-    if token.token_type == TokenType::EOF {
-        report(token.line as i32, " at end", message);
-    } else {
-        report(token.line as i32, &format!(" at '{}'", token.lexeme), message);
+    emit(&parser_diagnostic(token, message));
+}
+
+/// The diagnostic for an error at the token, errors at the end of the file
+/// say so as there is nothing to underline
+pub fn parser_diagnostic(token: &Token, message: &str) -> Diagnostic {
+    let mut diagnostic = Diagnostic::from_token(message, token);
+    if token.token_type == TokenType::EOF
+        && let Some(primary) = &mut diagnostic.primary
+    {
+        primary.message = "at end".to_string();
     }
-}
\ No newline at end of file
+    diagnostic
+}
@@ -1,10 +1,24 @@
-use crate::{literal::LiteralValue, token::Token};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-/**
+use crate::{
+    literal::LiteralValue,
+    token::{Span, Token},
+};
+
+/*
 * expression     → literal
                    | unary
                    | binary
-                   | grouping ;
+                   | grouping
+                   | variable
+                   | assign
+                   | logical
+                   | call
+                   | get
+                   | set
+                   | this
+                   | super
+                   | interpolation ;
 
    literal        → NUMBER | STRING | "true" | "false" | "nil" ;
    grouping       → "(" expression ")" ;
@@ -12,6 +26,15 @@ use crate::{literal::LiteralValue, token::Token};
    binary         → expression operator expression ;
    operator       → "==" | "!=" | "<" | "<=" | ">" | ">="
                     | "+"  | "-"  | "*" | "/" ;
+   variable       → IDENTIFIER ;
+   assign         → IDENTIFIER "=" expression ;
+   logical        → expression ( "and" | "or" ) expression ;
+   call           → expression "(" ( expression ( "," expression )* )? ")" ;
+   get            → expression "." IDENTIFIER ;
+   set            → expression "." IDENTIFIER "=" expression ;
+   this           → "this" ;
+   super          → "super" "." IDENTIFIER ;
+   interpolation  → "\"" ( string part | "${" expression "}" )* "\"" ;
 */
 
 #[derive(Debug)]
@@ -23,31 +46,128 @@ pub enum Expr {
     },
     Grouping {
         expression: Box<Expr>,
+        // Includes the parentheses
+        span: Span,
     },
     Literal {
         value: LiteralValue,
+        span: Span,
     },
     Unary {
         operator: Token,
         right: Box<Expr>,
     },
+    Variable {
+        // Identifies this node for the resolved scope depth
+        id: usize,
+        name: Token,
+    },
+    Assign {
+        id: usize,
+        name: Token,
+        value: Box<Expr>,
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        // The closing paren, its location is used for reporting runtime errors
+        paren: Token,
+        arguments: Vec<Expr>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    This {
+        // Resolved like a variable, `this` is bound in the method's closure
+        id: usize,
+        keyword: Token,
+    },
+    Super {
+        // Resolved like a variable, `super` is bound in the methods' closure
+        id: usize,
+        keyword: Token,
+        method: Token,
+    },
+    // A string with `${}`s. It is kept as its own node instead of being turned
+    // into `+`s so the AST prints back the way it was written, the interpreter
+    // joins the parts with the expressions turned into strings.
+    Interpolation {
+        // The string pieces and the expressions alternate, starting and ending
+        // with a piece which can be empty. The pieces are string literals.
+        parts: Vec<Expr>,
+        // From the opening quote to the closing one
+        span: Span,
+    },
+}
+
+/// Hands out a new id for the expressions that refer to a variable, ids are
+/// unique for the whole process so the resolved depths of separately parsed
+/// sources never collide
+pub fn next_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
 }
 
 // Visitor pattern implementation
 pub trait Visitor<R> {
-    fn visit_binary_expr(&self, left: &Expr, operator: &Token, right: &Expr) -> R;
-    fn visit_grouping_expr(&self, expression: &Expr) -> R;
-    fn visit_literal_expr(&self, value: String) -> R;
-    fn visit_unary_expr(&self, operator: &Token, right: &Expr) -> R;
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> R;
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> R;
+    fn visit_literal_expr(&mut self, value: &LiteralValue) -> R;
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> R;
+    fn visit_variable_expr(&mut self, id: usize, name: &Token) -> R;
+    fn visit_assign_expr(&mut self, id: usize, name: &Token, value: &Expr) -> R;
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> R;
+    fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> R;
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> R;
+    fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> R;
+    fn visit_this_expr(&mut self, id: usize, keyword: &Token) -> R;
+    fn visit_super_expr(&mut self, id: usize, keyword: &Token, method: &Token) -> R;
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) -> R;
 }
 
 impl Expr {
-    pub fn accept<R>(&self, visitor: &dyn Visitor<R>) -> R {
+    /// Where the expression is in the source, from its first token to its last
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => left.span().to(right.span()),
+            Expr::Grouping { span, .. } | Expr::Literal { span, .. } => *span,
+            Expr::Unary { operator, right } => operator.span.to(right.span()),
+            Expr::Variable { name, .. } => name.span,
+            Expr::Assign { name, value, .. } => name.span.to(value.span()),
+            Expr::Call { callee, paren, .. } => callee.span().to(paren.span),
+            Expr::Get { object, name } => object.span().to(name.span),
+            Expr::Set { object, value, .. } => object.span().to(value.span()),
+            Expr::This { keyword, .. } => keyword.span,
+            Expr::Super { keyword, method, .. } => keyword.span.to(method.span),
+            Expr::Interpolation { span, .. } => *span,
+        }
+    }
+
+    pub fn accept<R>(&self, visitor: &mut dyn Visitor<R>) -> R {
         match self {
             Expr::Binary {left, operator, right} => visitor.visit_binary_expr(left, operator, right),
-            Expr::Grouping { expression } => visitor.visit_grouping_expr(expression),
-            Expr::Literal { value } => visitor.visit_literal_expr(value.to_string()),
+            Expr::Grouping { expression, .. } => visitor.visit_grouping_expr(expression),
+            Expr::Literal { value, .. } => visitor.visit_literal_expr(value),
             Expr::Unary {operator, right } => visitor.visit_unary_expr(operator, right),
+            Expr::Variable { id, name } => visitor.visit_variable_expr(*id, name),
+            Expr::Assign { id, name, value } => visitor.visit_assign_expr(*id, name, value),
+            Expr::Logical { left, operator, right } => visitor.visit_logical_expr(left, operator, right),
+            Expr::Call { callee, paren, arguments } => visitor.visit_call_expr(callee, paren, arguments),
+            Expr::Get { object, name } => visitor.visit_get_expr(object, name),
+            Expr::Set { object, name, value } => visitor.visit_set_expr(object, name, value),
+            Expr::This { id, keyword } => visitor.visit_this_expr(*id, keyword),
+            Expr::Super { id, keyword, method } => visitor.visit_super_expr(*id, keyword, method),
+            Expr::Interpolation { parts, .. } => visitor.visit_interpolation_expr(parts),
         }
     }
 }
@@ -55,39 +175,123 @@ impl Expr {
 pub struct AstPrinter;
 
 impl AstPrinter {
-    pub fn print(&self, expr: Expr) -> String {
+    #[allow(dead_code)]
+    pub fn print(&mut self, expr: &Expr) -> String {
         expr.accept(self)
     }
 
-    fn parenthesize(&self, name: String, exprs: Vec<&Expr>) -> String {
+    pub(crate) fn parenthesize(&mut self, name: String, exprs: Vec<&Expr>) -> String {
         let mut r = String::new();
-        r.push_str("(");
+        r.push('(');
         r.push_str(&name);
         for e in &exprs {
-            r.push_str(" ");
+            r.push(' ');
             r.push_str(&e.accept(self));
         }
-        r.push_str(")");
+        r.push(')');
         r
     }
 }
 
 impl Visitor<String> for AstPrinter {
-    fn visit_binary_expr(&self, left: &Expr, operator: &Token, right: &Expr) -> String {
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
         self.parenthesize(operator.lexeme.clone(), vec![left, right])
     }
 
-    fn visit_grouping_expr(&self, expr: &Expr) -> String {
+    fn visit_grouping_expr(&mut self, expr: &Expr) -> String {
         self.parenthesize("group".to_string(), vec![expr])
     }
 
-    fn visit_literal_expr(&self, value: String) -> String {
-        value // check for null
+    fn visit_literal_expr(&mut self, value: &LiteralValue) -> String {
+        match value {
+            LiteralValue::Null => "nil".to_string(),
+            LiteralValue::String(value) => quote(value),
+            _ => value.to_string(),
+        }
     }
 
-    fn visit_unary_expr(&self, operator: &Token, right: &Expr) -> String {
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> String {
         self.parenthesize(operator.lexeme.clone(), vec![right])
     }
+
+    fn visit_variable_expr(&mut self, _id: usize, name: &Token) -> String {
+        name.lexeme.clone()
+    }
+
+    fn visit_assign_expr(&mut self, _id: usize, name: &Token, value: &Expr) -> String {
+        self.parenthesize(format!("= {}", name.lexeme), vec![value])
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        self.parenthesize(operator.lexeme.clone(), vec![left, right])
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> String {
+        let mut exprs = vec![callee];
+        exprs.extend(arguments);
+        self.parenthesize("call".to_string(), exprs)
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> String {
+        self.parenthesize(format!(". {}", name.lexeme), vec![object])
+    }
+
+    fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> String {
+        self.parenthesize(format!("= .{}", name.lexeme), vec![object, value])
+    }
+
+    fn visit_this_expr(&mut self, _id: usize, keyword: &Token) -> String {
+        keyword.lexeme.clone()
+    }
+
+    fn visit_super_expr(&mut self, _id: usize, keyword: &Token, method: &Token) -> String {
+        format!("(. {} {})", keyword.lexeme, method.lexeme)
+    }
+
+    // Printed back the way it is written, with the expressions printed as usual
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) -> String {
+        let mut r = String::new();
+        r.push('"');
+        for (index, part) in parts.iter().enumerate() {
+            match part {
+                Expr::Literal { value: LiteralValue::String(value), .. } if index % 2 == 0 => escape(value, &mut r),
+                _ => {
+                    r.push_str("${");
+                    r.push_str(&part.accept(self));
+                    r.push('}');
+                }
+            }
+        }
+        r.push('"');
+        r
+    }
+}
+
+// A string as it would be written in the source, escaping whatever the
+// scanner decodes so it reads back as the same value
+fn quote(value: &str) -> String {
+    let mut r = String::with_capacity(value.len() + 2);
+    r.push('"');
+    escape(value, &mut r);
+    r.push('"');
+    r
+}
+
+fn escape(value: &str, r: &mut String) {
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => r.push_str("\\n"),
+            '\t' => r.push_str("\\t"),
+            '\r' => r.push_str("\\r"),
+            '\\' => r.push_str("\\\\"),
+            '"' => r.push_str("\\\""),
+            '\0' => r.push_str("\\0"),
+            '$' if chars.peek() == Some(&'{') => r.push_str("\\$"),
+            c if c.is_control() => r.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => r.push(c),
+        }
+    }
 }
 
 
@@ -100,21 +304,23 @@ mod tests {
     fn test_printer() {
         let expression = Expr::Binary {
             left: Box::new(Expr::Unary {
-                operator: Token::new(TokenType::MINUS,"-".to_string(), LiteralValue::Null, 1),
+                operator: Token::new(TokenType::MINUS, "-".to_string(), LiteralValue::Null, 1, 1, Span::default()),
                 right: Box::new(Expr::Literal {
                     value: LiteralValue::Number(123.),
+                    span: Span::default(),
                 }),
             }),
-            operator: Token::new(TokenType::STAR, "*".to_string(), LiteralValue::Null, 1),
+            operator: Token::new(TokenType::STAR, "*".to_string(), LiteralValue::Null, 1, 1, Span::default()),
             right: Box::new(Expr::Grouping {
                 expression: Box::new(Expr::Literal {
                     value: LiteralValue::Number(45.67),
+                    span: Span::default(),
                 }),
+                span: Span::default(),
             }),
         };
-        let printer = AstPrinter;
-        // println!("This is the {}", printer.print(expression));
+        let mut printer = AstPrinter;
 
-        assert_eq!(printer.print(expression), "(* (- 123) (group 45.67))");
+        assert_eq!(printer.print(&expression), "(* (- 123) (group 45.67))");
     }
 }
\ No newline at end of file
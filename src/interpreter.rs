@@ -0,0 +1,797 @@
+use std::{cell::RefCell, collections::HashMap, panic, rc::Rc, thread};
+
+use crate::{
+    callable::{self, LoxCallable, LoxFunction, NativeFunction},
+    class::{LoxClass, LoxInstance},
+    environment::Environment,
+    error::LoxErrors,
+    expr::{self, AstPrinter, Expr},
+    literal::LiteralValue,
+    stmt::{self, Stmt},
+    token::Token,
+    token_type::TokenType,
+    trace::trace,
+    value::Value,
+};
+
+// Lox calls recurse on the native stack, a call past this depth is a runtime
+// error instead of overflowing the stack of the thread
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+// A Lox call takes a few kilobytes of native stack in release builds and over
+// ten in debug builds, this fits `MAX_CALL_DEPTH` calls with room to spare
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Runs `f` on a thread with a `STACK_SIZE` stack, which Lox code should be
+/// interpreted on so hitting `MAX_CALL_DEPTH` is an error and not an abort.
+/// Only calls are counted, evaluating an expression nested deeply enough,
+/// like a sum of a million terms, still overflows the stack and aborts
+pub fn with_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("Could not start the interpreter thread.");
+        handle.join().unwrap_or_else(|err| panic::resume_unwind(err))
+    })
+}
+
+// Tree-walking interpreter, evaluates the expressions into runtime values
+pub struct Interpreter {
+    // The outermost environment, unresolved variables are looked up here
+    globals: Rc<RefCell<Environment>>,
+    // The environment of the scope we are currently executing
+    environment: Rc<RefCell<Environment>>,
+    // Scope depth of every local variable expression, filled in by the resolver
+    locals: HashMap<usize, usize>,
+    // Number of calls currently being executed
+    call_depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        // Native functions live in the outermost environment
+        let mut globals = Environment::new();
+        globals.define(
+            "clock".to_string(),
+            Value::Callable(Rc::new(NativeFunction::new(0, callable::clock))),
+        );
+
+        let globals = Rc::new(RefCell::new(globals));
+        Self {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: HashMap::new(),
+            call_depth: 0,
+        }
+    }
+
+    pub fn globals(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.globals)
+    }
+
+    /// Called by the resolver for every variable expression that refers to a
+    /// local, `depth` is the number of scopes between the use and the declaration
+    pub fn resolve(&mut self, id: usize, depth: usize) {
+        self.locals.insert(id, depth);
+    }
+
+    fn look_up_variable(&self, id: usize, name: &Token) -> Result<Value, LoxErrors> {
+        match self.locals.get(&id) {
+            Some(distance) => self.environment.borrow().get_at(*distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), LoxErrors> {
+        for statement in statements {
+            self.execute(statement)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn evaluate(&mut self, expression: &Expr) -> Result<Value, LoxErrors> {
+        expression.accept(self)
+    }
+
+    fn execute(&mut self, statement: &Stmt) -> Result<(), LoxErrors> {
+        trace!(EXEC, "execute", stmt = AstPrinter.print_stmt(statement));
+        statement.accept(self)
+    }
+
+    /// Executes the statements inside the given environment, the previous
+    /// environment is restored afterwards even if one of them errors out
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), LoxErrors> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = statements.iter().try_for_each(|statement| self.execute(statement));
+        self.environment = previous;
+        result
+    }
+
+    fn number_operand(&self, operator: &Token, operand: &Value) -> Result<f64, LoxErrors> {
+        match operand {
+            Value::Number(n) => Ok(*n),
+            _ => Err(LoxErrors::RUNTIMEERROR(
+                operator.clone(),
+                "Operand must be a number.".to_string(),
+            )),
+        }
+    }
+
+    fn number_operands(&self, operator: &Token, left: &Value, right: &Value) -> Result<(f64, f64), LoxErrors> {
+        match (left, right) {
+            (Value::Number(l), Value::Number(r)) => Ok((*l, *r)),
+            _ => Err(LoxErrors::RUNTIMEERROR(
+                operator.clone(),
+                "Operands must be numbers.".to_string(),
+            )),
+        }
+    }
+}
+
+impl expr::Visitor<Result<Value, LoxErrors>> for Interpreter {
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Result<Value, LoxErrors> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+
+        match operator.token_type {
+            TokenType::PLUS => match (&left, &right) {
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
+                (Value::String(l), Value::String(r)) => Ok(Value::String(format!("{}{}", l, r))),
+                _ => Err(LoxErrors::RUNTIMEERROR(
+                    operator.clone(),
+                    "Operands must be two numbers or two strings.".to_string(),
+                )),
+            },
+            TokenType::MINUS => {
+                let (l, r) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Number(l - r))
+            }
+            TokenType::STAR => {
+                let (l, r) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Number(l * r))
+            }
+            TokenType::SLASH => {
+                let (l, r) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Number(l / r))
+            }
+            TokenType::GREATER => {
+                let (l, r) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Boolean(l > r))
+            }
+            TokenType::GREATER_EQUAL => {
+                let (l, r) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Boolean(l >= r))
+            }
+            TokenType::LESS => {
+                let (l, r) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Boolean(l < r))
+            }
+            TokenType::LESS_EQUAL => {
+                let (l, r) = self.number_operands(operator, &left, &right)?;
+                Ok(Value::Boolean(l <= r))
+            }
+            TokenType::EQUAL_EQUAL => Ok(Value::Boolean(left == right)),
+            TokenType::BANG_EQUAL => Ok(Value::Boolean(left != right)),
+            _ => Err(LoxErrors::UNEXPECTEDTOKENTYPEFOUND(operator.token_type.clone())),
+        }
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> Result<Value, LoxErrors> {
+        self.evaluate(expression)
+    }
+
+    fn visit_literal_expr(&mut self, value: &LiteralValue) -> Result<Value, LoxErrors> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Result<Value, LoxErrors> {
+        let right = self.evaluate(right)?;
+
+        match operator.token_type {
+            TokenType::MINUS => Ok(Value::Number(-self.number_operand(operator, &right)?)),
+            TokenType::BANG => Ok(Value::Boolean(!right.is_truthy())),
+            _ => Err(LoxErrors::UNEXPECTEDTOKENTYPEFOUND(operator.token_type.clone())),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, id: usize, name: &Token) -> Result<Value, LoxErrors> {
+        self.look_up_variable(id, name)
+    }
+
+    fn visit_assign_expr(&mut self, id: usize, name: &Token, value: &Expr) -> Result<Value, LoxErrors> {
+        let value = self.evaluate(value)?;
+
+        match self.locals.get(&id) {
+            Some(distance) => self
+                .environment
+                .borrow_mut()
+                .assign_at(*distance, name, value.clone())?,
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+        // Assignment is an expression, so it evaluates to the assigned value
+        Ok(value)
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Result<Value, LoxErrors> {
+        let left = self.evaluate(left)?;
+
+        // Short-circuit, the operand itself is returned rather than a boolean
+        if operator.token_type == TokenType::OR {
+            if left.is_truthy() {
+                return Ok(left);
+            }
+        } else if !left.is_truthy() {
+            return Ok(left);
+        }
+
+        self.evaluate(right)
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> Result<Value, LoxErrors> {
+        let callee = self.evaluate(callee)?;
+
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+
+        let function: Rc<dyn LoxCallable> = match callee {
+            Value::Callable(function) => function,
+            Value::Class(class) => class,
+            _ => {
+                return Err(LoxErrors::RUNTIMEERROR(
+                    paren.clone(),
+                    "Can only call functions and classes.".to_string(),
+                ))
+            }
+        };
+
+        if values.len() != function.arity() {
+            return Err(LoxErrors::RUNTIMEERROR(
+                paren.clone(),
+                format!("Expected {} arguments but got {}.", function.arity(), values.len()),
+            ));
+        }
+
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(LoxErrors::RUNTIMEERROR(paren.clone(), "Stack overflow.".to_string()));
+        }
+
+        trace!(EXEC, "call", callee = function.to_string(), arguments = values.len(), line = paren.line);
+        self.call_depth += 1;
+        let result = function.call(self, values);
+        self.call_depth -= 1;
+        result
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<Value, LoxErrors> {
+        match self.evaluate(object)? {
+            Value::Instance(instance) => LoxInstance::get(&instance, name),
+            _ => Err(LoxErrors::RUNTIMEERROR(
+                name.clone(),
+                "Only instances have properties.".to_string(),
+            )),
+        }
+    }
+
+    fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> Result<Value, LoxErrors> {
+        let Value::Instance(instance) = self.evaluate(object)? else {
+            return Err(LoxErrors::RUNTIMEERROR(
+                name.clone(),
+                "Only instances have fields.".to_string(),
+            ));
+        };
+
+        let value = self.evaluate(value)?;
+        instance.borrow_mut().set(name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_this_expr(&mut self, id: usize, keyword: &Token) -> Result<Value, LoxErrors> {
+        self.look_up_variable(id, keyword)
+    }
+
+    fn visit_super_expr(&mut self, id: usize, keyword: &Token, method: &Token) -> Result<Value, LoxErrors> {
+        // The resolver only lets `super` through inside a subclass method
+        let distance = *self.locals.get(&id).expect("'super' should have been resolved");
+        let Value::Class(superclass) = self.environment.borrow().get_at(distance, keyword)? else {
+            unreachable!("'super' is always bound to a class");
+        };
+
+        // `this` is bound in the scope right inside the one holding `super`
+        let this = Token::new(
+            TokenType::THIS,
+            "this".to_string(),
+            LiteralValue::Null,
+            keyword.line,
+            keyword.column,
+            keyword.span,
+        );
+        let object = self.environment.borrow().get_at(distance - 1, &this)?;
+
+        match superclass.find_method(&method.lexeme) {
+            Some(function) => Ok(Value::Callable(Rc::new(function.bind(object)))),
+            None => Err(LoxErrors::RUNTIMEERROR(
+                method.clone(),
+                format!("Undefined property '{}'.", method.lexeme),
+            )),
+        }
+    }
+
+    // Every part is evaluated in order and turned into the string `print`
+    // would show, the result is all of them joined together
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) -> Result<Value, LoxErrors> {
+        let mut value = String::new();
+        for part in parts {
+            value.push_str(&self.evaluate(part)?.to_string());
+        }
+        Ok(Value::String(value))
+    }
+}
+
+impl stmt::Visitor<Result<(), LoxErrors>> for Interpreter {
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> Result<(), LoxErrors> {
+        self.evaluate(expression)?;
+        Ok(())
+    }
+
+    fn visit_print_stmt(&mut self, expression: &Expr) -> Result<(), LoxErrors> {
+        let value = self.evaluate(expression)?;
+        println!("{}", value);
+        Ok(())
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> Result<(), LoxErrors> {
+        // Variables without an initializer are implicitly nil
+        let value = match initializer {
+            Some(expression) => self.evaluate(expression)?,
+            None => Value::Nil,
+        };
+
+        self.environment.borrow_mut().define(name.lexeme.clone(), value);
+        Ok(())
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<(), LoxErrors> {
+        let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+        self.execute_block(statements, Rc::new(RefCell::new(environment)))
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> Result<(), LoxErrors> {
+        if self.evaluate(condition)?.is_truthy() {
+            self.execute(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), LoxErrors> {
+        while self.evaluate(condition)?.is_truthy() {
+            self.execute(body)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Token], body: &Rc<Vec<Stmt>>) -> Result<(), LoxErrors> {
+        // The function captures the environment it is declared in, this is what makes closures work
+        let function = LoxFunction::new(
+            name.clone(),
+            params.to_vec(),
+            Rc::clone(body),
+            Rc::clone(&self.environment),
+            false,
+        );
+
+        self.environment
+            .borrow_mut()
+            .define(name.lexeme.clone(), Value::Callable(Rc::new(function)));
+        Ok(())
+    }
+
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: Option<&Expr>) -> Result<(), LoxErrors> {
+        let value = match value {
+            Some(expression) => self.evaluate(expression)?,
+            None => Value::Nil,
+        };
+
+        Err(LoxErrors::RETURN(value))
+    }
+
+    fn visit_class_stmt(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) -> Result<(), LoxErrors> {
+        let superclass = match superclass {
+            Some(expression) => match self.evaluate(expression)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    // The superclass is always a variable, point at its name
+                    let token = match expression {
+                        Expr::Variable { name, .. } => name,
+                        _ => name,
+                    };
+                    return Err(LoxErrors::RUNTIMEERROR(
+                        token.clone(),
+                        "Superclass must be a class.".to_string(),
+                    ));
+                }
+            },
+            None => None,
+        };
+
+        // Methods of a subclass get an extra scope where `super` is bound
+        let previous = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::with_enclosing(Rc::clone(&self.environment));
+            environment.define("super".to_string(), Value::Class(Rc::clone(superclass)));
+            self.environment = Rc::new(RefCell::new(environment));
+        }
+
+        let mut class_methods = HashMap::new();
+        for method in methods {
+            if let Stmt::Function { name, params, body, .. } = method {
+                let function = LoxFunction::new(
+                    name.clone(),
+                    params.clone(),
+                    Rc::clone(body),
+                    Rc::clone(&self.environment),
+                    name.lexeme == "init",
+                );
+                class_methods.insert(name.lexeme.clone(), Rc::new(function));
+            }
+        }
+
+        self.environment = previous;
+
+        let class = LoxClass::new(name.lexeme.clone(), superclass, class_methods);
+        self.environment
+            .borrow_mut()
+            .define(name.lexeme.clone(), Value::Class(Rc::new(class)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interpreter;
+    use crate::{
+        error::LoxErrors, expr::Expr, literal::LiteralValue, parser::Parser, resolver::Resolver,
+        scanner::Scanner, stmt::Stmt, token::{Span, Token}, token_type::TokenType, value::Value,
+    };
+
+    fn parse_expression(source: &str) -> Expr {
+        let tokens = Scanner::new(format!("{};", source)).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().expect("Could not parse sample code.");
+        match statements.into_iter().next() {
+            Some(Stmt::Expression { expression, .. }) => expression,
+            other => panic!("Expected an expression statement, got {:?}", other),
+        }
+    }
+
+    fn run(interpreter: &mut Interpreter, source: &str) -> Result<(), LoxErrors> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().expect("Could not parse sample code.");
+        Resolver::new(interpreter).resolve(&statements)?;
+        interpreter.interpret(&statements)
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Value {
+        let name = Token::new(TokenType::IDENTIFIER, name.to_string(), LiteralValue::Null, 1, 1, Span::default());
+        interpreter.globals.borrow().get(&name).unwrap()
+    }
+
+    fn eval(source: &str) -> Value {
+        Interpreter::new().evaluate(&parse_expression(source)).unwrap()
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let result = super::with_stack(|| {
+            let mut interpreter = Interpreter::new();
+            let source = "fun f(n) { if (n == 0) return 0; return f(n - 1) + 1; }\nvar a = f(999);\nf(5000);";
+            let result = run(&mut interpreter, source).map_err(|err| err.to_string());
+            // Values can't leave the thread, they are reference counted
+            (result, global(&interpreter, "a").to_string())
+        });
+
+        // Exactly `MAX_CALL_DEPTH` calls are still fine
+        assert_eq!(result, (Err("Stack overflow.\n[line 1]".to_string()), "999".to_string()));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("(1 + 2) * 3 - 4 / 2"), Value::Number(7.));
+        assert_eq!(eval("-(3)"), Value::Number(-3.));
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        assert_eq!(eval("\"foo\" + \"bar\""), Value::String("foobar".to_string()));
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert_eq!(
+            eval("\"${1 + 2} and ${nil}, ${\"in ${true}\"}!\""),
+            Value::String("3 and nil, in true!".to_string())
+        );
+        assert_eq!(eval("\"${2.5}\""), Value::String("2.5".to_string()));
+    }
+
+    #[test]
+    fn test_truthiness_and_equality() {
+        assert_eq!(eval("!nil"), Value::Boolean(true));
+        assert_eq!(eval("!0"), Value::Boolean(false));
+        assert_eq!(eval("1 < 2 == true"), Value::Boolean(true));
+        assert_eq!(eval("nil == false"), Value::Boolean(false));
+        assert_eq!(eval("\"a\" != \"a\""), Value::Boolean(false));
+    }
+
+    #[test]
+    fn test_comparison_requires_numbers() {
+        let expression = parse_expression("\n\"a\" < 1");
+
+        match Interpreter::new().evaluate(&expression) {
+            Err(LoxErrors::RUNTIMEERROR(token, message)) => {
+                assert_eq!(token.lexeme, "<");
+                assert_eq!(token.line, 2);
+                assert_eq!(message, "Operands must be numbers.");
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "var a = 1; var b; var c = a = 2; a = a + c;").unwrap();
+
+        assert_eq!(global(&interpreter, "a"), Value::Number(4.));
+        assert_eq!(global(&interpreter, "b"), Value::Nil);
+        assert_eq!(global(&interpreter, "c"), Value::Number(2.));
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let mut interpreter = Interpreter::new();
+
+        match run(&mut interpreter, "var a = 1;\nprint x;") {
+            Err(LoxErrors::RUNTIMEERROR(token, message)) => {
+                assert_eq!(token.lexeme, "x");
+                assert_eq!(token.line, 2);
+                assert_eq!(message, "Undefined variable 'x'.");
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+        assert!(run(&mut interpreter, "y = 1;").is_err());
+    }
+
+    #[test]
+    fn test_block_scoping_and_shadowing() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "var a = \"global\"; var b = 1;
+             { var a = \"inner\"; b = a; { var a = 3; } }
+             var c = a;",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "b"), Value::String("inner".to_string()));
+        assert_eq!(global(&interpreter, "c"), Value::String("global".to_string()));
+    }
+
+    #[test]
+    fn test_logical_operators_return_operands() {
+        assert_eq!(eval("nil or \"yes\""), Value::String("yes".to_string()));
+        assert_eq!(eval("1 or undefined"), Value::Number(1.));
+        assert_eq!(eval("nil and undefined"), Value::Nil);
+        assert_eq!(eval("1 and 2"), Value::Number(2.));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "var sum = 0;
+             for (var i = 0; i < 5; i = i + 1) { if (i == 2) sum = sum + 100; else sum = sum + i; }
+             var n = 0;
+             while (n < 3) n = n + 1;",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "sum"), Value::Number(108.));
+        assert_eq!(global(&interpreter, "n"), Value::Number(3.));
+    }
+
+    #[test]
+    fn test_functions_and_recursion() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+             var a = fib(10);
+             fun noReturn() { 1; }
+             var b = noReturn();
+             fun early() { while (true) { return \"done\"; } }
+             var c = early();",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "a"), Value::Number(55.));
+        assert_eq!(global(&interpreter, "b"), Value::Nil);
+        assert_eq!(global(&interpreter, "c"), Value::String("done".to_string()));
+        assert_eq!(global(&interpreter, "fib").to_string(), "<fn fib>");
+        assert_eq!(global(&interpreter, "clock").to_string(), "<native fn>");
+    }
+
+    #[test]
+    fn test_closures_capture_their_environment() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "fun makeCounter() { var i = 0; fun count() { i = i + 1; return i; } return count; }
+             var counter = makeCounter();
+             counter();
+             var a = counter();
+             var b = makeCounter()();",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "a"), Value::Number(2.));
+        assert_eq!(global(&interpreter, "b"), Value::Number(1.));
+    }
+
+    #[test]
+    fn test_closures_bind_to_resolved_scope() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "var a = \"global\"; var first; var second;
+             {
+               fun showA() { return a; }
+               first = showA();
+               var a = \"block\";
+               second = showA();
+             }",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "first"), Value::String("global".to_string()));
+        assert_eq!(global(&interpreter, "second"), Value::String("global".to_string()));
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let mut interpreter = Interpreter::new();
+
+        match run(&mut interpreter, "\"not a function\"();") {
+            Err(LoxErrors::RUNTIMEERROR(token, message)) => {
+                assert_eq!(token.lexeme, ")");
+                assert_eq!(message, "Can only call functions and classes.");
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+
+        match run(&mut interpreter, "fun f(a, b) {} f(1);") {
+            Err(LoxErrors::RUNTIMEERROR(_, message)) => {
+                assert_eq!(message, "Expected 2 arguments but got 1.")
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_classes_and_instances() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "class Counter {
+               init(start) { this.count = start; }
+               increment() { this.count = this.count + 1; return this; }
+             }
+             var counter = Counter(10);
+             counter.increment().increment();
+             var count = counter.count;
+             var method = counter.increment;
+             method();
+             var later = counter.count;
+             var reinit = counter.init(0);
+             var name = Counter;",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "count"), Value::Number(12.));
+        assert_eq!(global(&interpreter, "later"), Value::Number(13.));
+        assert_eq!(global(&interpreter, "reinit"), global(&interpreter, "counter"));
+        assert_eq!(global(&interpreter, "counter").to_string(), "Counter instance");
+        assert_eq!(global(&interpreter, "name").to_string(), "Counter");
+    }
+
+    #[test]
+    fn test_inheritance_and_super() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "class A {
+               init(name) { this.name = name; }
+               greet() { return \"A \" + this.name; }
+               inherited() { return \"from A\"; }
+             }
+             class B < A {
+               greet() { return \"B and \" + super.greet(); }
+             }
+             class C < B {}
+             var c = C(\"c\");
+             var greeting = c.greet();
+             var inherited = c.inherited();",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "greeting"), Value::String("B and A c".to_string()));
+        assert_eq!(global(&interpreter, "inherited"), Value::String("from A".to_string()));
+    }
+
+    #[test]
+    fn test_superclass_must_be_a_class() {
+        let mut interpreter = Interpreter::new();
+
+        match run(&mut interpreter, "var NotAClass = \"nope\";\nclass B < NotAClass {}") {
+            Err(LoxErrors::RUNTIMEERROR(token, message)) => {
+                assert_eq!(token.lexeme, "NotAClass");
+                assert_eq!(token.line, 2);
+                assert_eq!(message, "Superclass must be a class.");
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_property_errors() {
+        let mut interpreter = Interpreter::new();
+
+        match run(&mut interpreter, "class A {}\nvar a = A();\nprint a.missing;") {
+            Err(LoxErrors::RUNTIMEERROR(token, message)) => {
+                assert_eq!(token.line, 3);
+                assert_eq!(message, "Undefined property 'missing'.");
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+
+        match run(&mut interpreter, "var n = 1; print n.x;") {
+            Err(LoxErrors::RUNTIMEERROR(_, message)) => {
+                assert_eq!(message, "Only instances have properties.")
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+
+        match run(&mut interpreter, "\"str\".x = 1;") {
+            Err(LoxErrors::RUNTIMEERROR(_, message)) => {
+                assert_eq!(message, "Only instances have fields.")
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_block_restores_scope_after_error() {
+        let mut interpreter = Interpreter::new();
+        assert!(run(&mut interpreter, "var a = 1; { var a = 2; -\"x\"; }").is_err());
+
+        run(&mut interpreter, "var b = a;").unwrap();
+        assert_eq!(global(&interpreter, "b"), Value::Number(1.));
+    }
+}
@@ -0,0 +1,21 @@
+// The interpreter as a library, the binary in main.rs is the command line
+// front end on top of it and the benchmarks use it directly
+
+pub mod error;
+pub mod scanner;
+pub mod token;
+pub mod token_type;
+pub mod expr;
+pub mod parser;
+pub mod stmt;
+pub mod literal;
+pub mod value;
+pub mod interpreter;
+pub mod environment;
+pub mod callable;
+pub mod resolver;
+pub mod class;
+pub mod repl;
+pub mod cli;
+pub mod trace;
+pub mod diagnostic;
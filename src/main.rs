@@ -1,53 +1,102 @@
-use std::{env, fs};
+use std::{env, process};
 
-use expr::AstPrinter;
-use parser::Parser;
-use scanner::Scanner;
+use rust_interpreter::{
+    cli::{self, Command, Input, Options},
+    diagnostic,
+    error::{report_error, LoxErrors},
+    expr::AstPrinter,
+    interpreter::{self, Interpreter},
+    parser,
+    repl,
+    resolver::Resolver,
+    scanner::Scanner,
+    trace,
+};
 
-mod error;
-mod scanner;
-mod token;
-mod token_type;
-mod expr;
-mod parser;
-mod literal;
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match cli::parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, cli::USAGE);
+            process::exit(cli::EXIT_USAGE);
+        }
+    };
 
-pub static HAD_ERROR: bool = false;
+    // Lox calls recurse on the native stack, so everything that runs Lox code
+    // does so on a thread with a big one
+    interpreter::with_stack(move || run_command(options));
+}
 
-fn main() {
-    // For now there will be two things in the CLI:
-    // 1. Path(-p) -> Give the exact path to the file. (For now we will use this)
-    // 2. File(-f) -> Give the file that you want to interpret. I will add this later on
-    let cli_options: Vec<String> = env::args().collect();
-    if cli_options.get(1).eq(&Some(&"-p".to_string())) {
-        // RUN THE FILE
-        get_file_contents(cli_options.get(2).unwrap());
-    } else {
-        panic!("Wrong CLI options used: {:?}", cli_options.get(1));
+fn run_command(options: Options) {
+    diagnostic::set_format(options.error_format);
+    trace::enable_from_env();
+    options.trace.into_iter().for_each(trace::enable);
+
+    let result = match options.command {
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Command::Repl => {
+            repl::run_prompt();
+            return;
+        }
+        Command::Run(input) => run(read_input(&input)),
+        Command::Tokens(input) => print_tokens(read_input(&input)),
+        Command::Ast(input) => print_ast(read_input(&input)),
+        Command::Check(input) => check(read_input(&input)),
+    };
+
+    if let Err(err) = result {
+        report_error(&err);
+        process::exit(err.exit_code());
     }
 }
 
-fn get_file_contents(path: &str) {
-    let file_content = fs::read_to_string(path);
-
-    match file_content {
+fn read_input(input: &Input) -> String {
+    match input.read() {
         Ok(contents) => {
-            println!("FILE CONTENTS: {:?}", contents);
-            run(contents);
-        },
+            diagnostic::set_source(input.name(), &contents);
+            contents
+        }
         Err(err) => {
-            print!("No contents found: {err}");
+            eprintln!("Could not read {}: {}", input.name(), err);
+            process::exit(cli::EXIT_NO_INPUT);
         }
     }
 }
 
-fn run(source: String) {
-    let mut scanner = Scanner::new(source.to_string());
-    let tokens_list = scanner.scan_tokens().unwrap();
-    println!("TOKENSSS!!!: {:#?}", &tokens_list);
-    let mut parser = Parser::new(tokens_list);
-    if let Some(expression) = parser.parse() {
-        let printer = AstPrinter;
-        println!("{}", printer.print(expression));
+fn run(source: String) -> Result<(), LoxErrors> {
+    let statements = parser::parse(Scanner::new(source), false)?;
+    let mut interpreter = Interpreter::new();
+    Resolver::new(&mut interpreter).resolve(&statements)?;
+    interpreter.interpret(&statements)
+}
+
+fn print_tokens(source: String) -> Result<(), LoxErrors> {
+    for token in Scanner::new(source).scan_tokens()? {
+        for doc in token.trivia() {
+            println!("{}", doc);
+        }
+        println!("{}", token);
+    }
+    Ok(())
+}
+
+fn print_ast(source: String) -> Result<(), LoxErrors> {
+    let statements = parser::parse(Scanner::new(source), false)?;
+
+    let mut printer = AstPrinter;
+    for statement in &statements {
+        println!("{}", printer.print_stmt(statement));
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+// Everything up to running the script, so only the static errors show up
+fn check(source: String) -> Result<(), LoxErrors> {
+    let statements = parser::parse(Scanner::new(source), false)?;
+    let mut interpreter = Interpreter::new();
+    Resolver::new(&mut interpreter).resolve(&statements)
+}
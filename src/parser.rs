@@ -1,50 +1,101 @@
-#[allow(unused_variables)]
+use std::rc::Rc;
 
-use crate::{error::{parser_error, LoxErrors}, literal::LiteralValue};
-use crate::{expr::Expr, token::Token, token_type::TokenType};
+use crate::{diagnostic::{self, Label}, error::{parser_diagnostic, parser_error, scanner_error, LoxErrors}, literal::LiteralValue};
+use crate::{expr::{self, AstPrinter, Expr}, scanner::Scanner, stmt::Stmt, token::{Span, Token}, token_type::TokenType, trace::trace};
 
-/**
+/*
 *  Parser grammer
-*  expression     → equality ;
+*  program        → declaration* EOF ;
+*  declaration    → classDecl
+*                   | funDecl
+*                   | varDecl
+*                   | statement ;
+*  classDecl      → "class" IDENTIFIER ( "<" IDENTIFIER )?
+*                   "{" function* "}" ;
+*  funDecl        → "fun" function ;
+*  function       → IDENTIFIER "(" parameters? ")" block ;
+*  parameters     → IDENTIFIER ( "," IDENTIFIER )* ;
+*  varDecl        → "var" IDENTIFIER ( "=" expression )? ";" ;
+*  statement      → exprStmt
+*                   | forStmt
+*                   | ifStmt
+*                   | printStmt
+*                   | returnStmt
+*                   | whileStmt
+*                   | block ;
+*  exprStmt       → expression ";" ;
+*  forStmt        → "for" "(" ( varDecl | exprStmt | ";" )
+*                   expression? ";"
+*                   expression? ")" statement ;
+*  ifStmt         → "if" "(" expression ")" statement
+*                   ( "else" statement )? ;
+*  printStmt      → "print" expression ";" ;
+*  returnStmt     → "return" expression? ";" ;
+*  whileStmt      → "while" "(" expression ")" statement ;
+*  block          → "{" declaration* "}" ;
+*  expression     → assignment ;
+*  assignment     → ( call "." )? IDENTIFIER "=" assignment
+*                   | logic_or ;
+*  logic_or       → logic_and ( "or" logic_and )* ;
+*  logic_and      → equality ( "and" equality )* ;
 *  equality       → comparison ( ( "!=" | "==" ) comparison )* ;
 *  comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
 *  term           → factor ( ( "-" | "+" ) factor )* ;
 *  factor         → unary ( ( "/" | "*" ) unary )* ;
 *  unary          → ( "!" | "-" ) unary
-*                   | primary ;
-*  primary        → NUMBER | STRING | "true" | "false" | "nil"
-                    | "(" expression ")" ;
+*                   | call ;
+*  call           → primary ( "(" arguments? ")" | "." IDENTIFIER )* ;
+*  arguments      → expression ( "," expression )* ;
+*  primary        → NUMBER | STRING | "true" | "false" | "nil" | "this"
+                    | "(" expression ")" | IDENTIFIER
+                    | "super" "." IDENTIFIER | interpolation ;
+*  interpolation  → INTERPOLATION_START expression
+                    ( INTERPOLATION_MIDDLE expression )* INTERPOLATION_END ;
 */
 
-/**
+/*
  * Associativity table:
  *
- * Name	        Operators	Associates
- * Equality	    == !=	    Left
- * Comparison	> >= < <=	Left
- * Term	-       +	        Left
- * Factor	    / *	        Left
- * Unary	    ! -	        Right
+ * Name          Operators     Associates
+ * Assignment    =             Right
+ * Logic or      or            Left
+ * Logic and     and           Left
+ * Equality      == !=         Left
+ * Comparison    > >= < <=     Left
+ * Term          - +           Left
+ * Factor        / *           Left
+ * Unary         ! -           Right
  */
 
-/**
+/*
  * Precedence table:
  * expression     → ...
+ * assignment     → ...
+ * logic_or       → ...
+ * logic_and      → ...
  * equality       → ...
  * comparison     → ...
  * term           → ...
  * factor         → ...
  * unary          → ...
+ * call           → ...
  * primary        → ...
 
 The precedence if from lower to higher, meaning the primary has the highest precedence
 */
 
+// Upper bound on the number of arguments of a call and parameters of a function
+const MAX_ARGUMENTS: usize = 255;
+
 // Main struct used for parsing stuff!
 pub struct Parser {
     tokens_list: Vec<Token>,
     // The token where we are at now!
-    current: u16,
+    current: usize,
+    // In the REPL the ';' after a trailing expression can be left out
+    repl: bool,
+    // Every syntax error found so far, they are reported as they are found
+    errors: Vec<LoxErrors>,
 }
 
 impl Parser {
@@ -52,28 +103,436 @@ impl Parser {
         Self {
             tokens_list,
             current: 0,
+            repl: false,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn new_repl(tokens_list: Vec<Token>) -> Self {
+        Self {
+            repl: true,
+            ..Self::new(tokens_list)
+        }
+    }
+
+    /// Parses the whole program and returns the first syntax error if there
+    /// were any, all of them have been reported by then
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, LoxErrors> {
+        let (statements, mut errors) = self.parse_all();
+
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
+    /// Parses the whole program, recovering from every syntax error. Returns
+    /// the statements that could be parsed along with all the errors.
+    pub fn parse_all(&mut self) -> (Vec<Stmt>, Vec<LoxErrors>) {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration_or_synchronize() {
+                trace!(PARSER, "statement", ast = AstPrinter.print_stmt(&statement), span = statement.span());
+                statements.push(statement);
+            }
+        }
+
+        (statements, std::mem::take(&mut self.errors))
+    }
+
+    // A declaration, on a syntax error it gets recorded and we skip ahead to
+    // the next statement so the rest of the code can still be checked
+    fn declaration_or_synchronize(&mut self) -> Option<Stmt> {
+        match self.declaration() {
+            Ok(statement) => Some(statement),
+            Err(err) => {
+                self.errors.push(err);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // A declaration, the doc comments scanned before it are on its first token
+    fn declaration(&mut self) -> Result<Stmt, LoxErrors> {
+        if self.match_tokens(&[TokenType::CLASS]) {
+            return self.class_declaration();
+        }
+
+        if self.match_tokens(&[TokenType::FUN]) {
+            let docs = self.previous().trivia().to_vec();
+            return self.function("function", self.previous().span, docs);
+        }
+
+        if self.match_tokens(&[TokenType::VAR]) {
+            return self.var_declaration();
+        }
+
+        self.statement()
+    }
+
+    // A class declaration
+    fn class_declaration(&mut self) -> Result<Stmt, LoxErrors> {
+        let start = self.previous().span;
+        let docs = self.previous().trivia().to_vec();
+        let name = self.consume(TokenType::IDENTIFIER, "Expect class name.")?;
+
+        let mut superclass = None;
+        if self.match_tokens(&[TokenType::LESS]) {
+            let name = self.consume(TokenType::IDENTIFIER, "Expect superclass name.")?;
+            superclass = Some(Expr::Variable { id: expr::next_id(), name });
+        }
+
+        self.consume(TokenType::LEFT_BRACE, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            let docs = self.peek().trivia().to_vec();
+            methods.push(self.function("method", self.peek().span, docs)?);
+        }
+
+        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after class body.")?;
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+            docs,
+            span: self.span_from(start),
+        })
+    }
+
+    // A function declaration, `kind` is only used for the error messages.
+    // Methods have no `fun` keyword so the caller tells where the span starts,
+    // and hands over the doc comments.
+    fn function(&mut self, kind: &str, start: Span, docs: Vec<Token>) -> Result<Stmt, LoxErrors> {
+        let name = self.consume(TokenType::IDENTIFIER, &format!("Expect {} name.", kind))?;
+        self.consume(TokenType::LEFT_PAREN, &format!("Expect '(' after {} name.", kind))?;
+
+        let mut params = Vec::new();
+        if !self.check(&TokenType::RIGHT_PAREN) {
+            loop {
+                // The parser is not confused by this, so no need to synchronize
+                if params.len() >= MAX_ARGUMENTS {
+                    let err = self.error(self.peek(), "Can't have more than 255 parameters.");
+                    self.errors.push(err);
+                }
+
+                params.push(self.consume(TokenType::IDENTIFIER, "Expect parameter name.")?);
+                if !self.match_tokens(&[TokenType::COMMA]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after parameters.")?;
+
+        self.consume(TokenType::LEFT_BRACE, &format!("Expect '{{' before {} body.", kind))?;
+        let body = Rc::new(self.block()?);
+
+        Ok(Stmt::Function {
+            name,
+            params,
+            body,
+            docs,
+            span: self.span_from(start),
+        })
+    }
+
+    // A variable declaration
+    fn var_declaration(&mut self) -> Result<Stmt, LoxErrors> {
+        let start = self.previous().span;
+        let docs = self.previous().trivia().to_vec();
+        let name = self.consume(TokenType::IDENTIFIER, "Expect variable name.")?;
+
+        let mut initializer = None;
+        if self.match_tokens(&[TokenType::EQUAL]) {
+            initializer = Some(self.expression()?);
+        }
+
+        self.consume(TokenType::SEMICOLON, "Expect ';' after variable declaration.")?;
+        Ok(Stmt::Var {
+            name,
+            initializer,
+            docs,
+            span: self.span_from(start),
+        })
+    }
+
+    // A statement
+    fn statement(&mut self) -> Result<Stmt, LoxErrors> {
+        if self.match_tokens(&[TokenType::FOR]) {
+            return self.for_statement();
+        }
+
+        if self.match_tokens(&[TokenType::IF]) {
+            return self.if_statement();
+        }
+
+        if self.match_tokens(&[TokenType::PRINT]) {
+            return self.print_statement();
+        }
+
+        if self.match_tokens(&[TokenType::RETURN]) {
+            return self.return_statement();
+        }
+
+        if self.match_tokens(&[TokenType::WHILE]) {
+            return self.while_statement();
+        }
+
+        if self.match_tokens(&[TokenType::LEFT_BRACE]) {
+            let start = self.previous().span;
+            let statements = self.block()?;
+            return Ok(Stmt::Block {
+                statements,
+                span: self.span_from(start),
+            });
+        }
+
+        self.expression_statement()
+    }
+
+    // The statements of a block, the opening '{' is already consumed
+    fn block(&mut self) -> Result<Vec<Stmt>, LoxErrors> {
+        let mut statements = Vec::new();
+
+        while !self.check(&TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            statements.extend(self.declaration_or_synchronize());
+        }
+
+        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    // A for statement, there is no for node in the AST so this gets desugared
+    // into a while loop wrapped in blocks for the initializer and increment
+    fn for_statement(&mut self) -> Result<Stmt, LoxErrors> {
+        // The nodes it is desugared into all get the span of the whole loop
+        let start = self.previous().span;
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_tokens(&[TokenType::SEMICOLON]) {
+            None
+        } else if self.match_tokens(&[TokenType::VAR]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let mut condition = None;
+        if !self.check(&TokenType::SEMICOLON) {
+            condition = Some(self.expression()?);
+        }
+        self.consume(TokenType::SEMICOLON, "Expect ';' after loop condition.")?;
+
+        let mut increment = None;
+        if !self.check(&TokenType::RIGHT_PAREN) {
+            increment = Some(self.expression()?);
+        }
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+        let span = self.span_from(start);
+
+        if let Some(increment) = increment {
+            let increment = Stmt::Expression {
+                span: increment.span(),
+                expression: increment,
+            };
+            body = Stmt::Block {
+                statements: vec![body, increment],
+                span,
+            };
+        }
+
+        // An omitted condition loops forever
+        let condition = condition.unwrap_or(Expr::Literal {
+            value: LiteralValue::Boolean(true),
+            span: start,
+        });
+        body = Stmt::While {
+            condition,
+            body: Box::new(body),
+            span,
+        };
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block {
+                statements: vec![initializer, body],
+                span,
+            };
         }
+
+        Ok(body)
     }
 
-    pub fn parse(&mut self) -> Option<Expr> {
-        self.expression().ok()
+    // An if statement, the else binds to the nearest if as we eagerly look for
+    // it before returning
+    fn if_statement(&mut self) -> Result<Stmt, LoxErrors> {
+        let start = self.previous().span;
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let mut else_branch = None;
+        if self.match_tokens(&[TokenType::ELSE]) {
+            else_branch = Some(Box::new(self.statement()?));
+        }
+
+        Ok(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            span: self.span_from(start),
+        })
+    }
+
+    // A while statement
+    fn while_statement(&mut self) -> Result<Stmt, LoxErrors> {
+        let start = self.previous().span;
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after condition.")?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While {
+            condition,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    // A print statement
+    fn print_statement(&mut self) -> Result<Stmt, LoxErrors> {
+        let start = self.previous().span;
+        let expression = self.expression()?;
+        self.consume(TokenType::SEMICOLON, "Expect ';' after value.")?;
+        Ok(Stmt::Print {
+            expression,
+            span: self.span_from(start),
+        })
+    }
+
+    // A return statement
+    fn return_statement(&mut self) -> Result<Stmt, LoxErrors> {
+        let keyword = self.previous();
+
+        let mut value = None;
+        if !self.check(&TokenType::SEMICOLON) {
+            value = Some(self.expression()?);
+        }
+
+        self.consume(TokenType::SEMICOLON, "Expect ';' after return value.")?;
+        Ok(Stmt::Return {
+            span: self.span_from(keyword.span),
+            keyword,
+            value,
+        })
+    }
+
+    // An expression statement
+    fn expression_statement(&mut self) -> Result<Stmt, LoxErrors> {
+        let start = self.peek().span;
+        let expression = self.expression()?;
+        if !(self.repl && self.is_at_end()) {
+            self.consume(TokenType::SEMICOLON, "Expect ';' after expression.")?;
+        }
+        Ok(Stmt::Expression {
+            expression,
+            span: self.span_from(start),
+        })
     }
 
     // An expression expr
     fn expression(&mut self) -> Result<Expr, LoxErrors> {
-        return Ok(self.equality());
+        self.assignment()
+    }
+
+    // An assignment expr, the left side is parsed as an expression first and
+    // only then checked to be a valid assignment target (l-value)
+    fn assignment(&mut self) -> Result<Expr, LoxErrors> {
+        let expr = self.or()?;
+
+        if self.match_tokens(&[TokenType::EQUAL]) {
+            let equals = self.previous();
+            // Recursing here makes assignment right-associative
+            let value = self.assignment()?;
+
+            match expr {
+                Expr::Variable { name, .. } => {
+                    return Ok(Expr::Assign {
+                        id: expr::next_id(),
+                        name,
+                        value: Box::new(value),
+                    });
+                }
+                // A property access on the left turns into setting the property
+                Expr::Get { object, name } => {
+                    return Ok(Expr::Set {
+                        object,
+                        name,
+                        value: Box::new(value),
+                    });
+                }
+                // Reported but not worth synchronizing over, the parser is not confused
+                target => {
+                    let diagnostic = parser_diagnostic(&equals, "Invalid assignment target.")
+                        .with_help("only a variable like `a` or a property like `a.b` can be assigned to");
+                    diagnostic::emit(&diagnostic);
+                    self.errors.push(LoxErrors::PARSEERROR(equals));
+                    return Ok(target);
+                }
+            }
+        }
+
+        Ok(expr)
+    }
+
+    // A logic or expr
+    fn or(&mut self) -> Result<Expr, LoxErrors> {
+        let mut expr = self.and()?;
+
+        while self.match_tokens(&[TokenType::OR]) {
+            let operator = self.previous();
+            let right = self.and()?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    // A logic and expr
+    fn and(&mut self) -> Result<Expr, LoxErrors> {
+        let mut expr = self.equality()?;
+
+        while self.match_tokens(&[TokenType::AND]) {
+            let operator = self.previous();
+            let right = self.equality()?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
     }
 
     // An equality expr
-    fn equality(&mut self) -> Expr {
+    fn equality(&mut self) -> Result<Expr, LoxErrors> {
         // Comparison
-        let mut expr = self.comparison();
+        let mut expr = self.comparison()?;
 
         while self.match_tokens(&[TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL]) {
             // Operator
             let operator = self.previous();
             // Comparsion
-            let expr_temp = self.comparison();
+            let expr_temp = self.comparison()?;
             expr = Expr::Binary {
                 left: Box::new(expr),
                 operator,
@@ -81,12 +540,12 @@ impl Parser {
             };
         }
 
-        return expr;
+        Ok(expr)
     }
 
     // A comparison expr
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
+    fn comparison(&mut self) -> Result<Expr, LoxErrors> {
+        let mut expr = self.term()?;
 
         while self.match_tokens(&[
             TokenType::GREATER,
@@ -95,7 +554,7 @@ impl Parser {
             TokenType::LESS_EQUAL,
         ]) {
             let operator = self.previous();
-            let temp_term = self.term();
+            let temp_term = self.term()?;
 
             expr = Expr::Binary {
                 left: Box::new(expr),
@@ -103,16 +562,16 @@ impl Parser {
                 right: Box::new(temp_term),
             };
         }
-        return expr;
+        Ok(expr)
     }
 
     // A term expr
-    fn term(&mut self) -> Expr {
-        let mut expr_factor = self.factor();
+    fn term(&mut self) -> Result<Expr, LoxErrors> {
+        let mut expr_factor = self.factor()?;
 
         while self.match_tokens(&[TokenType::MINUS, TokenType::PLUS]) {
             let operator = self.previous();
-            let factor = self.factor();
+            let factor = self.factor()?;
             expr_factor = Expr::Binary {
                 left: Box::new(expr_factor),
                 operator,
@@ -120,16 +579,16 @@ impl Parser {
             };
         }
 
-        return expr_factor;
+        Ok(expr_factor)
     }
 
     // A factor expr
-    fn factor(&mut self) -> Expr {
-        let mut expr_unary = self.unary();
+    fn factor(&mut self) -> Result<Expr, LoxErrors> {
+        let mut expr_unary = self.unary()?;
 
         while self.match_tokens(&[TokenType::SLASH, TokenType::STAR]) {
             let operator = self.previous();
-            let unary = self.unary();
+            let unary = self.unary()?;
 
             expr_unary = Expr::Binary {
                 left: Box::new(expr_unary),
@@ -138,48 +597,116 @@ impl Parser {
             };
         }
 
-        return expr_unary;
+        Ok(expr_unary)
     }
 
     // A unary expr
-    fn unary(&mut self) -> Expr {
+    fn unary(&mut self) -> Result<Expr, LoxErrors> {
         if self.match_tokens(&[TokenType::BANG, TokenType::MINUS]) {
             let operator = self.previous();
-            let unary = self.unary();
+            let unary = self.unary()?;
 
-            return Expr::Unary {
+            return Ok(Expr::Unary {
                 operator,
                 right: Box::new(unary),
-            };
+            });
+        }
+
+        self.call()
+    }
+
+    // A call expr, calls and property accesses can be chained like `a.b(1)(2)`
+    fn call(&mut self) -> Result<Expr, LoxErrors> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_tokens(&[TokenType::LEFT_PAREN]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_tokens(&[TokenType::DOT]) {
+                let name = self.consume(TokenType::IDENTIFIER, "Expect property name after '.'.")?;
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    // The arguments of a call, the opening '(' is already consumed
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, LoxErrors> {
+        let mut arguments = Vec::new();
+
+        if !self.check(&TokenType::RIGHT_PAREN) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    let err = self.error(self.peek(), "Can't have more than 255 arguments.");
+                    self.errors.push(err);
+                }
+
+                arguments.push(self.expression()?);
+                if !self.match_tokens(&[TokenType::COMMA]) {
+                    break;
+                }
+            }
         }
 
-        // TODO: Handle the error here
-        return self.primary().unwrap();
+        let paren = self.consume(TokenType::RIGHT_PAREN, "Expect ')' after arguments.")?;
+
+        Ok(Expr::Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
     }
 
     // A primary expr
     fn primary(&mut self) -> Result<Expr, LoxErrors> {
 
         if self.match_tokens(&[TokenType::FALSE]) {
-            return Ok(Expr::Literal { value: LiteralValue::Boolean(false) })
+            return Ok(Expr::Literal { value: LiteralValue::Boolean(false), span: self.previous().span })
         }
 
         if self.match_tokens(&[TokenType::TRUE]) {
-            return Ok(Expr::Literal { value: LiteralValue::Boolean(true) })
+            return Ok(Expr::Literal { value: LiteralValue::Boolean(true), span: self.previous().span })
         }
 
         if self.match_tokens(&[TokenType::NIL]) {
-            return Ok(Expr::Literal { value: LiteralValue::Null })
+            return Ok(Expr::Literal { value: LiteralValue::Null, span: self.previous().span })
         }
 
         if self.match_tokens(&[TokenType::NUMBER, TokenType::STRING]) {
-            return Ok(Expr::Literal { value: self.previous().literal })
+            let token = self.previous();
+            return Ok(Expr::Literal { value: token.literal, span: token.span })
+        }
+
+        if self.match_tokens(&[TokenType::INTERPOLATION_START]) {
+            return self.interpolation();
+        }
+
+        if self.match_tokens(&[TokenType::SUPER]) {
+            let keyword = self.previous();
+            self.consume(TokenType::DOT, "Expect '.' after 'super'.")?;
+            let method = self.consume(TokenType::IDENTIFIER, "Expect superclass method name.")?;
+            return Ok(Expr::Super { id: expr::next_id(), keyword, method });
+        }
+
+        if self.match_tokens(&[TokenType::THIS]) {
+            return Ok(Expr::This { id: expr::next_id(), keyword: self.previous() })
+        }
+
+        if self.match_tokens(&[TokenType::IDENTIFIER]) {
+            return Ok(Expr::Variable { id: expr::next_id(), name: self.previous() })
         }
 
         if self.match_tokens(&[TokenType::LEFT_PAREN]) {
+            let start = self.previous().span;
             let expr= self.expression()?;
-            self.consume(TokenType::RIGHT_PAREN, "Expect ')' after expression.")?;
-            return Ok(Expr::Grouping { expression: Box::new(expr) });
+            let paren = self.consume(TokenType::RIGHT_PAREN, "Expect ')' after expression.")?;
+            return Ok(Expr::Grouping { expression: Box::new(expr), span: start.to(paren.span) });
         }
 
         Err(self.error(self.peek(), "Expect expression."))
@@ -218,7 +745,32 @@ impl Parser {
         // Ok(expr)
     }
 
-    // Synchronize
+    // A string with `${}`s, the string pieces and the expressions go into the
+    // parts in turn so the pieces can be printed back where they were
+    fn interpolation(&mut self) -> Result<Expr, LoxErrors> {
+        let start = self.previous().span;
+        let mut parts = Vec::new();
+        let mut piece = self.previous();
+        loop {
+            parts.push(Expr::Literal { value: piece.literal, span: piece.span });
+            if piece.token_type == TokenType::INTERPOLATION_END {
+                break;
+            }
+
+            parts.push(self.expression()?);
+
+            piece = if self.match_tokens(&[TokenType::INTERPOLATION_MIDDLE]) {
+                self.previous()
+            } else {
+                self.consume(TokenType::INTERPOLATION_END, "Expect '}' after interpolated expression.")?
+            };
+        }
+
+        Ok(Expr::Interpolation { parts, span: self.span_from(start) })
+    }
+
+    // Panic mode recovery, throws tokens away until we are probably at the
+    // start of the next statement
     fn synchronize(&mut self) {
         self.advance();
 
@@ -241,6 +793,11 @@ impl Parser {
         }
     }
 
+    // The span from `start` up to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
+    }
+
     // Wrapper around parser error
     fn error(&self, token: &Token, message: &str) -> LoxErrors {
         parser_error(token, message);
@@ -253,26 +810,58 @@ impl Parser {
             return Ok(self.advance());
         }
 
-        Err(LoxErrors::UNEXPECTEDTOKENTYPEFOUND(token_type))
+        // Point at the opening one when a closing paren or brace is missing
+        let mut diagnostic = parser_diagnostic(self.peek(), message);
+        if let Some(opening) = self.unclosed_delimiter(&token_type) {
+            let label = format!("unclosed '{}' opened here", opening.lexeme);
+            diagnostic = diagnostic.with_label(Label::new(opening, &label));
+        }
+        diagnostic::emit(&diagnostic);
+
+        Err(LoxErrors::PARSEERROR(self.peek().clone()))
+    }
+
+    // The '(' or '{' that the missing closing token would have closed, found by
+    // walking back over the tokens we have consumed
+    fn unclosed_delimiter(&self, closing: &TokenType) -> Option<&Token> {
+        let opening = match closing {
+            TokenType::RIGHT_PAREN => TokenType::LEFT_PAREN,
+            TokenType::RIGHT_BRACE => TokenType::LEFT_BRACE,
+            _ => return None,
+        };
+
+        let mut depth = 0;
+        for token in self.tokens_list[..self.current].iter().rev() {
+            if token.token_type == *closing {
+                depth += 1;
+            } else if token.token_type == opening {
+                if depth == 0 {
+                    return Some(token);
+                }
+                depth -= 1;
+            }
+        }
+
+        None
     }
 
     // Match all the tokens that we need our current token to be!
     fn match_tokens(&mut self, array_of_tokens: &[TokenType]) -> bool {
         for token in array_of_tokens {
-            if self.check(&token) {
+            if self.check(token) {
                 // Consume the token
                 self.advance();
                 return true;
             }
         }
 
-        return false;
+        false
     }
 
     /// TODO(SAFETY): Check if unwrap() here is safe or not?
     fn previous(&self) -> Token {
         self.tokens_list
-            .get((self.current - 1) as usize)
+            .get(self.current - 1)
             .unwrap()
             .clone()
     }
@@ -285,15 +874,15 @@ impl Parser {
         }
 
         // Check the current token is of the same type that we needed
-        println!("INSIDE CHECK: PEEK: {:?} and token_type that we are checking: {:?}", &self.peek().token_type, token_type);
-        // println!("INSIDE CHECK ACTUAL");
-        return self.peek().token_type.eq(token_type);
+        self.peek().token_type.eq(token_type)
     }
 
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             // Consuming the current token
-            self.current = self.current + 1;
+            let token = self.peek();
+            trace!(PARSER, "advance", kind = token.token_type, lexeme = token.lexeme, line = token.line);
+            self.current += 1;
         }
 
         // Returning the previous token (Current token has been updated!)
@@ -302,7 +891,7 @@ impl Parser {
 
     fn is_at_end(&self) -> bool {
         self.tokens_list
-            .get(self.current as usize)
+            .get(self.current)
             .unwrap()
             .token_type
             == TokenType::EOF
@@ -310,31 +899,343 @@ impl Parser {
 
     // Peek the current token
     pub fn peek(&self) -> &Token {
-        // println!("PEEK FUNCTION!!! : {:?}", self.current as usize);
-        self.tokens_list.get(self.current as usize).unwrap()
+        self.tokens_list.get(self.current).unwrap()
+    }
+}
+
+/// Scans and parses the whole source, reporting every lexical and syntax error
+/// in one pass. The first error is returned if there were any.
+pub fn parse(mut scanner: Scanner, repl: bool) -> Result<Vec<Stmt>, LoxErrors> {
+    let (tokens, scan_errors) = scanner.scan_all();
+    scan_errors.iter().for_each(scanner_error);
+
+    // The parser reports its errors as it finds them
+    let mut parser = if repl { Parser::new_repl(tokens) } else { Parser::new(tokens) };
+    let (statements, parse_errors) = parser.parse_all();
+
+    match scan_errors.into_iter().chain(parse_errors).next() {
+        Some(err) => Err(err),
+        None => Ok(statements),
     }
 }
 
+#[cfg(test)]
 mod tests {
     use crate::{
-        expr::{self, AstPrinter}, scanner::Scanner, token::Token
+        error::LoxErrors, expr::AstPrinter, scanner::Scanner, stmt::Stmt, token::Span,
+        token_type::TokenType,
     };
 
     use super::Parser;
 
-    use crate::token_type::TokenType::{EOF, EQUAL_EQUAL, IDENTIFIER};
-    use crate::literal::LiteralValue;
-
     #[test]
     fn test_equality() {
-         let mut scanner = Scanner::new("-123 * 45.67".to_string());
+         let mut scanner = Scanner::new("-123 * 45.67;".to_string());
          let tokens = scanner.scan_tokens().unwrap();
-         println!("THESE ARE THE TOKENS: {:?}", tokens);
  
          let mut parser = Parser::new(tokens);
-         let expression = parser.parse().expect("Could not parse sample code.");
-         let printer = AstPrinter;
- 
-         assert_eq!(printer.print(expression), "(* (- 123) 45.67)");
+         let statements = parser.parse().expect("Could not parse sample code.");
+         let mut printer = AstPrinter;
+
+         match statements.into_iter().next() {
+             Some(Stmt::Expression { expression, .. }) => {
+                 assert_eq!(printer.print(&expression), "(* (- 123) 45.67)")
+             }
+             other => panic!("Expected an expression statement, got {:?}", other),
+         }
+    }
+
+    #[test]
+    fn test_program_of_statements() {
+        let tokens = Scanner::new("print 1;\n2 + 3;\nprint \"a\";".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(statements[0], Stmt::Print { .. }));
+        assert!(matches!(statements[1], Stmt::Expression { .. }));
+        assert!(matches!(statements[2], Stmt::Print { .. }));
+    }
+
+    #[test]
+    fn test_assignment_is_right_associative() {
+        let tokens = Scanner::new("a = b = 1;".to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        match statements.into_iter().next() {
+            Some(Stmt::Expression { expression, .. }) => {
+                assert_eq!(AstPrinter.print(&expression), "(= a (= b 1))")
+            }
+            other => panic!("Expected an expression statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        let tokens = Scanner::new("a + b = 1;".to_string()).scan_tokens().unwrap();
+        assert!(Parser::new(tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_nested_blocks() {
+        let tokens = Scanner::new("{ var a = 1; { print a; } }".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        match &statements[..] {
+            [Stmt::Block { statements, .. }] => {
+                assert!(matches!(statements[0], Stmt::Var { .. }));
+                assert!(matches!(statements[1], Stmt::Block { .. }));
+            }
+            other => panic!("Expected a single block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unclosed_block() {
+        let tokens = Scanner::new("{ print 1;".to_string()).scan_tokens().unwrap();
+        assert!(Parser::new(tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_logical_precedence() {
+        let tokens = Scanner::new("a or b and c;".to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        match statements.into_iter().next() {
+            Some(Stmt::Expression { expression, .. }) => {
+                assert_eq!(AstPrinter.print(&expression), "(or a (and b c))")
+            }
+            other => panic!("Expected an expression statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dangling_else_binds_to_nearest_if() {
+        let tokens = Scanner::new("if (a) if (b) print 1; else print 2;".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        match &statements[..] {
+            [Stmt::If { then_branch, else_branch: None, .. }] => {
+                assert!(matches!(**then_branch, Stmt::If { else_branch: Some(_), .. }))
+            }
+            other => panic!("Expected an if without else, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_for_desugars_to_while() {
+        let tokens = Scanner::new("for (var i = 0; i < 3; i = i + 1) print i;".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        match &statements[..] {
+            [Stmt::Block { statements, .. }] => {
+                assert!(matches!(statements[0], Stmt::Var { .. }));
+                assert!(matches!(statements[1], Stmt::While { .. }));
+            }
+            other => panic!("Expected a desugared block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_function_declaration_and_calls() {
+        let tokens = Scanner::new("fun add(a, b) { return a + b; } add(1, 2)(3);".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        match &statements[..] {
+            [Stmt::Function { name, params, body, .. }, Stmt::Expression { expression, .. }] => {
+                assert_eq!(name.lexeme, "add");
+                assert_eq!(params.len(), 2);
+                assert!(matches!(body[0], Stmt::Return { value: Some(_), .. }));
+                assert_eq!(
+                    AstPrinter.print(expression),
+                    "(call (call add 1 2) 3)"
+                );
+            }
+            other => panic!("Expected a function and a call, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let source = "/// A point.\nclass Point {\n  /// Its x.\n  getX() { return 1; }\n}\n/// Origin.\nvar origin;\n/// Lost.\nprint 1;";
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        let docs = |statement: &Stmt| -> Vec<String> {
+            statement.docs().iter().map(|doc| doc.lexeme.clone()).collect()
+        };
+        match &statements[..] {
+            [class @ Stmt::Class { methods, .. }, var, print] => {
+                assert_eq!(docs(class), vec!["/// A point."]);
+                assert_eq!(docs(&methods[0]), vec!["/// Its x."]);
+                assert_eq!(docs(var), vec!["/// Origin."]);
+                assert!(docs(print).is_empty());
+            }
+            other => panic!("Expected a class, a variable and a print, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_too_many_arguments() {
+        let arguments = vec!["1"; 256].join(", ");
+        let tokens = Scanner::new(format!("f({});", arguments)).scan_tokens().unwrap();
+        assert!(Parser::new(tokens).parse().is_err());
+
+        let arguments = vec!["1"; 255].join(", ");
+        let tokens = Scanner::new(format!("f({});", arguments)).scan_tokens().unwrap();
+        assert!(Parser::new(tokens).parse().is_ok());
+    }
+
+    #[test]
+    fn test_class_declaration_and_properties() {
+        let tokens = Scanner::new(
+            "class Point { init(x) { this.x = x; } getX() { return this.x; } } a.b.c = d.e();"
+                .to_string(),
+        )
+        .scan_tokens()
+        .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        match &statements[..] {
+            [Stmt::Class { name, superclass: None, methods, .. }, Stmt::Expression { expression, .. }] => {
+                assert_eq!(name.lexeme, "Point");
+                assert_eq!(methods.len(), 2);
+                assert_eq!(
+                    AstPrinter.print(expression),
+                    "(= .c (. b a) (call (. e d)))"
+                );
+            }
+            other => panic!("Expected a class and a property set, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_subclass_and_super() {
+        let tokens = Scanner::new("class B < A { m() { return super.m(); } }".to_string())
+            .scan_tokens()
+            .unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        match &statements[..] {
+            [Stmt::Class { superclass: Some(superclass), methods, .. }] => {
+                assert_eq!(AstPrinter.print(superclass), "A");
+                assert!(matches!(methods[0], Stmt::Function { .. }));
+            }
+            other => panic!("Expected a subclass, got {:?}", other),
+        }
+
+        let tokens = Scanner::new("super;".to_string()).scan_tokens().unwrap();
+        assert!(Parser::new(tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let tokens = Scanner::new("print 1".to_string()).scan_tokens().unwrap();
+        assert!(Parser::new(tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_spans() {
+        let source = "var a = (1 + 2);\nfor (;;) print a.b(c);";
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        let text = |span: Span| &source[span.start..span.end];
+        assert_eq!(text(statements[0].span()), "var a = (1 + 2);");
+        assert_eq!(text(statements[1].span()), "for (;;) print a.b(c);");
+
+        match &statements[..] {
+            [Stmt::Var { initializer: Some(initializer), .. }, Stmt::While { body, .. }] => {
+                assert_eq!(text(initializer.span()), "(1 + 2)");
+                match &**body {
+                    Stmt::Print { expression, .. } => assert_eq!(text(expression.span()), "a.b(c)"),
+                    other => panic!("Expected a print statement, got {:?}", other),
+                }
+            }
+            other => panic!("Expected a var and a loop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unclosed_delimiter() {
+        let tokens = Scanner::new("f((a), { b".to_string()).scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.current = 8;
+
+        let paren = parser.unclosed_delimiter(&TokenType::RIGHT_PAREN).unwrap();
+        assert_eq!((paren.lexeme.as_str(), paren.column), ("(", 2));
+        let brace = parser.unclosed_delimiter(&TokenType::RIGHT_BRACE).unwrap();
+        assert_eq!((brace.lexeme.as_str(), brace.column), ("{", 8));
+        assert!(parser.unclosed_delimiter(&TokenType::SEMICOLON).is_none());
+    }
+
+    #[test]
+    fn test_multi_megabyte_source() {
+        // Well past 65,535 bytes, lines and tokens
+        let source: String = (0..150_000).map(|i| format!("var v{} = {} + 1;\n", i, i)).collect();
+        assert!(source.len() > 3_000_000);
+
+        let tokens = Scanner::new(source.clone()).scan_tokens().unwrap();
+        assert_eq!(tokens.len(), 150_000 * 7 + 1);
+
+        let last = &tokens[tokens.len() - 2];
+        assert_eq!((last.lexeme.as_str(), last.line), (";", 150_000));
+        assert_eq!(last.span.end, source.len() - 1);
+
+        let statements = Parser::new(tokens).parse().unwrap();
+        assert_eq!(statements.len(), 150_000);
+        match statements.last() {
+            Some(Stmt::Var { name, .. }) => assert_eq!((name.lexeme.as_str(), name.line), ("v149999", 150_000)),
+            other => panic!("Expected a var declaration, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reports_every_error() {
+        let tokens = Scanner::new(
+            "print 1 +; var = 2; { print (; print 3; } a + b = 4; fun f() { return 5; }".to_string(),
+        )
+        .scan_tokens()
+        .unwrap();
+        let (statements, errors) = Parser::new(tokens).parse_all();
+
+        let lexemes: Vec<String> = errors
+            .into_iter()
+            .map(|err| match err {
+                LoxErrors::PARSEERROR(token) => token.lexeme,
+                other => panic!("Expected a parse error, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(lexemes, vec![";", "=", ";", "="]);
+
+        // The statements around the errors are still there
+        match &statements[..] {
+            [Stmt::Block { statements: block, .. }, Stmt::Expression { .. }, Stmt::Function { .. }] => {
+                assert!(matches!(block[..], [Stmt::Print { .. }]));
+            }
+            other => panic!("Expected the recovered statements, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_repl_trailing_expression_without_semicolon() {
+        let tokens = Scanner::new("var a = 1; a + 2".to_string()).scan_tokens().unwrap();
+        assert!(Parser::new(tokens.clone()).parse().is_err());
+
+        let statements = Parser::new_repl(tokens).parse().unwrap();
+        assert!(matches!(statements[..], [Stmt::Var { .. }, Stmt::Expression { .. }]));
+
+        // Only the last expression may leave it out
+        let tokens = Scanner::new("1 2".to_string()).scan_tokens().unwrap();
+        assert!(Parser::new_repl(tokens).parse().is_err());
     }
 }
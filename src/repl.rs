@@ -0,0 +1,295 @@
+use std::{cell::RefCell, env, path::PathBuf, rc::Rc};
+
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, Helper,
+};
+
+use crate::{
+    diagnostic,
+    environment::Environment,
+    error::{report_error, LoxErrors},
+    interpreter::Interpreter,
+    parser,
+    resolver::Resolver,
+    scanner::{self, Scanner},
+    stmt::Stmt,
+    token_type::TokenType,
+};
+
+// Name of the history file kept in the home directory
+const HISTORY_FILE: &str = ".lox_history";
+
+/// Interactive prompt, every line is run in the same session so declarations
+/// stick around for the rest of it
+pub fn run_prompt() {
+    let mut session = Session::new();
+
+    let mut editor = match Editor::<LoxHelper, DefaultHistory>::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("Could not start the prompt: {}", err);
+            return;
+        }
+    };
+    editor.set_helper(Some(LoxHelper {
+        globals: session.interpreter.globals(),
+    }));
+
+    let history = history_path();
+    if let Some(path) = &history {
+        // There is no history yet on the first run
+        let _ = editor.load_history(path);
+    }
+
+    // Holds the lines of an input that is spread across multiple lines
+    let mut buffer = String::new();
+
+    loop {
+        let prompt = if buffer.is_empty() { "> " } else { "... " };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                buffer.push_str(&line);
+                buffer.push('\n');
+            }
+            // Ctrl-C drops the current input
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            // Ctrl-D
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                eprintln!("Could not read the input: {}", err);
+                break;
+            }
+        }
+
+        if needs_more_input(&buffer) {
+            continue;
+        }
+
+        let source = std::mem::take(&mut buffer);
+        if !source.trim().is_empty() {
+            let _ = editor.add_history_entry(source.trim_end());
+        }
+
+        // An error only ends the current input, never the session
+        if let Err(err) = session.run_line(source) {
+            report_error(&err);
+        }
+    }
+
+    if let Some(path) = &history
+        && let Err(err) = editor.save_history(path)
+    {
+        eprintln!("Could not save the history: {}", err);
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+// Editor helper that completes keywords and the globals the interpreter
+// currently knows about
+struct LoxHelper {
+    globals: Rc<RefCell<Environment>>,
+}
+
+impl LoxHelper {
+    fn candidates(&self, prefix: &str) -> Vec<String> {
+        let globals = self.globals.borrow();
+        let mut candidates: Vec<String> = scanner::keywords()
+            .map(str::to_string)
+            .chain(globals.names().cloned())
+            .filter(|candidate| candidate.starts_with(prefix))
+            .collect();
+
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = word_start(line, pos);
+        let prefix = &line[start..pos];
+        if prefix.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+
+        Ok((start, self.candidates(prefix)))
+    }
+}
+
+// The word under the cursor starts after the last non identifier character,
+// which can be more than one byte long
+fn word_start(line: &str, pos: usize) -> usize {
+    line[..pos]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !(c.is_ascii_alphanumeric() || *c == '_'))
+        .map_or(0, |(index, c)| index + c.len_utf8())
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {}
+
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
+
+/// The inputs of a prompt, run one after the other against the same
+/// interpreter. They are kept as one source so an error in code from an
+/// earlier input still points at where that code is.
+pub struct Session {
+    interpreter: Interpreter,
+    // Every input so far, each one starting on a line of its own
+    source: String,
+    // Line the next input starts on
+    line: usize,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+            source: String::new(),
+            line: 1,
+        }
+    }
+
+    /// Runs one input of the prompt, the value of a lone expression is printed
+    pub fn run_line(&mut self, input: String) -> Result<(), LoxErrors> {
+        let (offset, line) = (self.source.len(), self.line);
+        self.source.push_str(&input);
+        if !self.source.ends_with('\n') {
+            self.source.push('\n');
+        }
+        self.line += self.source[offset..].matches('\n').count();
+
+        diagnostic::set_source("<repl>", &self.source);
+        let statements = parser::parse(Scanner::new_at(self.source.clone(), offset, line), true)?;
+        Resolver::new(&mut self.interpreter).resolve(&statements)?;
+
+        if let [Stmt::Expression { expression, .. }] = &statements[..] {
+            println!("{}", self.interpreter.evaluate(expression)?);
+            return Ok(());
+        }
+
+        self.interpreter.interpret(&statements)
+    }
+}
+
+/// An input continues on the next line while a string is left open or there
+/// are more opening than closing parentheses and braces
+fn needs_more_input(source: &str) -> bool {
+    // Other errors are left for running the input to report
+    let (tokens, errors) = Scanner::new(source.to_string()).scan_all();
+    if errors.iter().any(|err| matches!(err, LoxErrors::UNTERMINATEDSTRING(..) | LoxErrors::UNTERMINATEDCOMMENT(..))) {
+        return true;
+    }
+
+    let mut depth = 0;
+    for token in tokens {
+        match token.token_type {
+            TokenType::LEFT_PAREN | TokenType::LEFT_BRACE => depth += 1,
+            TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACE => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{needs_more_input, word_start, LoxHelper, Session};
+    use crate::{diagnostic::Diagnostic, error::LoxErrors};
+
+    #[test]
+    fn test_needs_more_input() {
+        assert!(needs_more_input("fun f() {\n"));
+        assert!(needs_more_input("print (1 +\n"));
+        assert!(needs_more_input("print \"multi\nline"));
+        assert!(!needs_more_input("fun f() {}\n"));
+        assert!(!needs_more_input("print 1;\n"));
+        // Too many closing ones is an error for the parser to report
+        assert!(!needs_more_input("}\n"));
+    }
+
+    #[test]
+    fn test_completion_uses_interpreter_state() {
+        let mut session = Session::new();
+        let helper = LoxHelper {
+            globals: session.interpreter.globals(),
+        };
+
+        assert_eq!(helper.candidates("wh"), vec!["while"]);
+        assert!(helper.candidates("count").is_empty());
+
+        session.run_line("var counter = 0; fun countdown() {}".to_string()).unwrap();
+        assert_eq!(helper.candidates("count"), vec!["countdown", "counter"]);
+        assert_eq!(helper.candidates("cl"), vec!["class", "clock"]);
+    }
+
+    #[test]
+    fn test_word_start() {
+        assert_eq!(word_start("print cl", 8), 6);
+        assert_eq!(word_start("cl", 2), 0);
+        assert_eq!(word_start("print é", "print é".len()), "print é".len());
+        assert_eq!(word_start("print \"café x", "print \"café x".len()), "print \"café ".len());
+        assert_eq!(word_start("print é+co", "print é+co".len()), "print é+".len());
+    }
+
+    #[test]
+    fn test_session_survives_errors() {
+        let mut session = Session::new();
+
+        session.run_line("var a = 1;".to_string()).unwrap();
+        assert!(session.run_line("a +;".to_string()).is_err());
+        assert!(session.run_line("print 1 @ +;".to_string()).is_err());
+        assert!(session.run_line("-\"a\";".to_string()).is_err());
+        assert!(session.run_line("{ var b = 2; undefined; }".to_string()).is_err());
+        session.run_line("a = a + 1".to_string()).unwrap();
+        session.run_line("fun f() { return a; }".to_string()).unwrap();
+        session.run_line("print f();".to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_errors_point_into_earlier_inputs() {
+        let mut session = Session::new();
+
+        session.run_line("var a = 1;".to_string()).unwrap();
+        session.run_line("fun f() {\n  return -\"a\";\n}\n".to_string()).unwrap();
+        let Err(LoxErrors::RUNTIMEERROR(token, message)) = session.run_line("f();\n".to_string()) else {
+            panic!("Expected a runtime error.");
+        };
+
+        assert_eq!((token.line, token.column), (3, 10));
+        assert_eq!(&session.source[token.span.start..token.span.end], "-");
+        assert_eq!(
+            Diagnostic::from_token(&message, &token).render("<repl>", Some(&session.source), false),
+            "\
+error: Operand must be a number.
+ --> <repl>:3:10
+  |
+3 |   return -\"a\";
+  |          ^
+"
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    error::{parser_error, LoxErrors},
+    expr::{self, Expr},
+    interpreter::Interpreter,
+    literal::LiteralValue,
+    stmt::{self, Stmt},
+    token::Token,
+};
+
+// What kind of function body we are resolving, used to catch stray returns
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionType {
+    NONE,
+    FUNCTION,
+    METHOD,
+    INITIALIZER,
+}
+
+// Whether we are inside a class body, used to catch stray `this` and `super`
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassType {
+    NONE,
+    CLASS,
+    SUBCLASS,
+}
+
+// Static pass that runs between the parser and the interpreter. It works out
+// how many scopes away every local variable was declared and hands that over to
+// the interpreter, reporting the scoping errors it finds along the way.
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    // Stack of the block scopes, the value tells if the variable is fully
+    // defined (its initializer has been resolved). Globals are not tracked.
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<LoxErrors>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        Self {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::NONE,
+            current_class: ClassType::NONE,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Resolves the statements, every error is reported as soon as it is
+    /// found and the first one is returned
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Result<(), LoxErrors> {
+        self.resolve_statements(statements);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors.remove(0))
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &Stmt) {
+        statement.accept(self)
+    }
+
+    fn resolve_expression(&mut self, expression: &Expr) {
+        expression.accept(self)
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Stmt], function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    // Looks for the variable from the innermost scope outwards, if it is not
+    // found it is left unresolved and treated as a global
+    fn resolve_local(&mut self, id: usize, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.lexeme) {
+                self.interpreter.resolve(id, depth);
+                return;
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // Adds the variable to the innermost scope, marked as not ready yet
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.lexeme) {
+            self.error(name, "Already a variable with this name in this scope.");
+            return;
+        }
+
+        scope.insert(name.lexeme.clone(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        parser_error(token, message);
+        self.errors.push(LoxErrors::RESOLVEERROR(token.clone()));
+    }
+}
+
+impl expr::Visitor<()> for Resolver<'_> {
+    fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        self.resolve_expression(left);
+        self.resolve_expression(right);
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) {
+        self.resolve_expression(expression);
+    }
+
+    fn visit_literal_expr(&mut self, _value: &LiteralValue) {}
+
+    fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) {
+        self.resolve_expression(right);
+    }
+
+    fn visit_variable_expr(&mut self, id: usize, name: &Token) {
+        if let Some(false) = self.scopes.last().and_then(|scope| scope.get(&name.lexeme)) {
+            self.error(name, "Can't read local variable in its own initializer.");
+        }
+
+        self.resolve_local(id, name);
+    }
+
+    fn visit_assign_expr(&mut self, id: usize, name: &Token, value: &Expr) {
+        self.resolve_expression(value);
+        self.resolve_local(id, name);
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        self.resolve_expression(left);
+        self.resolve_expression(right);
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) {
+        self.resolve_expression(callee);
+        for argument in arguments {
+            self.resolve_expression(argument);
+        }
+    }
+
+    // Properties are looked up dynamically, only the object needs resolving
+    fn visit_get_expr(&mut self, object: &Expr, _name: &Token) {
+        self.resolve_expression(object);
+    }
+
+    fn visit_set_expr(&mut self, object: &Expr, _name: &Token, value: &Expr) {
+        self.resolve_expression(value);
+        self.resolve_expression(object);
+    }
+
+    fn visit_this_expr(&mut self, id: usize, keyword: &Token) {
+        if self.current_class == ClassType::NONE {
+            self.error(keyword, "Can't use 'this' outside of a class.");
+            return;
+        }
+
+        self.resolve_local(id, keyword);
+    }
+
+    fn visit_super_expr(&mut self, id: usize, keyword: &Token, _method: &Token) {
+        match self.current_class {
+            ClassType::NONE => self.error(keyword, "Can't use 'super' outside of a class."),
+            ClassType::CLASS => {
+                self.error(keyword, "Can't use 'super' in a class with no superclass.")
+            }
+            ClassType::SUBCLASS => self.resolve_local(id, keyword),
+        }
+    }
+
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) {
+        for part in parts {
+            self.resolve_expression(part);
+        }
+    }
+}
+
+impl stmt::Visitor<()> for Resolver<'_> {
+    fn visit_expression_stmt(&mut self, expression: &Expr) {
+        self.resolve_expression(expression);
+    }
+
+    fn visit_print_stmt(&mut self, expression: &Expr) {
+        self.resolve_expression(expression);
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) {
+        // Declaring and defining separately catches `var a = a;`
+        self.declare(name);
+        if let Some(initializer) = initializer {
+            self.resolve_expression(initializer);
+        }
+        self.define(name);
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) {
+        self.begin_scope();
+        self.resolve_statements(statements);
+        self.end_scope();
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) {
+        self.resolve_expression(condition);
+        self.resolve_statement(then_branch);
+        if let Some(else_branch) = else_branch {
+            self.resolve_statement(else_branch);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) {
+        self.resolve_expression(condition);
+        self.resolve_statement(body);
+    }
+
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Token], body: &Rc<Vec<Stmt>>) {
+        // The name is defined before the body so functions can recurse
+        self.declare(name);
+        self.define(name);
+
+        self.resolve_function(params, body, FunctionType::FUNCTION);
+    }
+
+    fn visit_return_stmt(&mut self, keyword: &Token, value: Option<&Expr>) {
+        if self.current_function == FunctionType::NONE {
+            self.error(keyword, "Can't return from top-level code.");
+        }
+
+        if let Some(value) = value {
+            if self.current_function == FunctionType::INITIALIZER {
+                self.error(keyword, "Can't return a value from an initializer.");
+            }
+
+            self.resolve_expression(value);
+        }
+    }
+
+    fn visit_class_stmt(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::CLASS;
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            if let Expr::Variable { name: superclass_name, .. } = superclass
+                && superclass_name.lexeme == name.lexeme
+            {
+                self.error(superclass_name, "A class can't inherit from itself.");
+            }
+
+            self.current_class = ClassType::SUBCLASS;
+            self.resolve_expression(superclass);
+
+            // Methods of a subclass close over one more scope that holds `super`
+            self.begin_scope();
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert("super".to_string(), true);
+            }
+        }
+
+        // Methods close over a scope that holds `this`
+        self.begin_scope();
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert("this".to_string(), true);
+        }
+
+        for method in methods {
+            if let Stmt::Function { name, params, body, .. } = method {
+                let function_type = if name.lexeme == "init" {
+                    FunctionType::INITIALIZER
+                } else {
+                    FunctionType::METHOD
+                };
+                self.resolve_function(params, body, function_type);
+            }
+        }
+
+        self.end_scope();
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Resolver;
+    use crate::{error::LoxErrors, interpreter::Interpreter, parser::Parser, scanner::Scanner};
+
+    fn resolve(source: &str) -> Result<(), LoxErrors> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().expect("Could not parse sample code.");
+        let mut interpreter = Interpreter::new();
+        Resolver::new(&mut interpreter).resolve(&statements)
+    }
+
+    fn error_lexeme(source: &str) -> String {
+        match resolve(source) {
+            Err(LoxErrors::RESOLVEERROR(token)) => token.lexeme,
+            other => panic!("Expected a resolver error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_valid_program() {
+        assert!(resolve("var a = 1; { var b = a; } fun f(x) { return x; }").is_ok());
+    }
+
+    #[test]
+    fn test_local_in_own_initializer() {
+        assert_eq!(error_lexeme("{ var a = 1; { var a = a + 1; } }"), "a");
+    }
+
+    #[test]
+    fn test_redeclared_local() {
+        assert_eq!(error_lexeme("fun f(a) { var a; }"), "a");
+        // Globals can be redeclared freely
+        assert!(resolve("var a; var a;").is_ok());
+    }
+
+    #[test]
+    fn test_top_level_return() {
+        assert_eq!(error_lexeme("return 1;"), "return");
+    }
+
+    #[test]
+    fn test_this_outside_class() {
+        assert_eq!(error_lexeme("fun f() { return this; }"), "this");
+        assert!(resolve("class A { m() { return this; } }").is_ok());
+    }
+
+    #[test]
+    fn test_inheritance_errors() {
+        assert_eq!(error_lexeme("class A < A {}"), "A");
+        assert_eq!(error_lexeme("fun f() { super.m(); }"), "super");
+        assert_eq!(error_lexeme("class A { m() { super.m(); } }"), "super");
+        assert!(resolve("class A {} class B < A { m() { super.m(); } }").is_ok());
+    }
+
+    #[test]
+    fn test_return_value_from_initializer() {
+        assert_eq!(error_lexeme("class A { init() { return 1; } }"), "return");
+        assert!(resolve("class A { init() { return; } }").is_ok());
+    }
+}
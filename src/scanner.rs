@@ -3,19 +3,90 @@ use std::collections::HashMap;
 use lazy_static::lazy_static;
 
 use crate::literal::LiteralValue;
-use crate::token::Token;
-use crate::{error::LoxErrors, token_type::TokenType};
+use crate::token::{Span, Token};
+use crate::{error::{scanner_error, LoxErrors}, token_type::TokenType, trace::trace};
 
 // This would be the the Scanner object
 pub struct Scanner {
     // All the characters in the file
     pub source: String,
-    // Beginning of the current lexeme
-    start: u16,
-    // Character we are at currently of the lexeme
-    current: u16,
-    line: u16,
+    // Byte offset of the beginning of the current lexeme
+    start: usize,
+    // Byte offset of the character we are at currently of the lexeme, always
+    // on a character boundary
+    current: usize,
+    line: usize,
+    // Column of the current character, counted in characters rather than bytes
+    column: usize,
+    // Line and column of the beginning of the current lexeme, a string can
+    // span lines so these can differ from the current ones
+    start_line: usize,
+    start_column: usize,
     tokens: Vec<Token>,
+    // Every lexical error found so far
+    errors: Vec<LoxErrors>,
+    // Doc comments waiting for the token they document
+    trivia: Vec<Token>,
+    // The `${`s we are inside of, innermost last
+    interpolations: Vec<Interpolation>,
+}
+
+// A line of a `"""` string, as decoded
+#[derive(Default)]
+struct StringLine {
+    text: String,
+    // How many spaces and tabs it starts with, escaped ones are not counted
+    indent: usize,
+    // Has more than indentation
+    has_text: bool,
+}
+
+impl StringLine {
+    fn push(&mut self, character: char, literal: bool) {
+        if !self.has_text && literal && matches!(character, ' ' | '\t') {
+            self.indent += 1;
+        } else {
+            self.has_text = true;
+        }
+        self.text.push(character);
+    }
+}
+
+// Joins the lines of a `"""` string, with the indentation every line shares
+// removed. Text right after the opening quotes is kept as it is, a blank
+// first line and a blank last line (the one with the closing quotes) are
+// dropped, and other blank lines become empty.
+fn dedent(mut lines: Vec<StringLine>) -> String {
+    if lines.len() > 1 && lines.last().is_some_and(|line| !line.has_text) {
+        lines.pop();
+    }
+    let first = if lines.len() > 1 && !lines[0].has_text {
+        lines.remove(0);
+        None
+    } else {
+        Some(lines.remove(0).text)
+    };
+
+    let indent = lines.iter().filter(|line| line.has_text).map(|line| line.indent).min().unwrap_or(0);
+    let rest = lines.into_iter().map(|line| {
+        if line.has_text {
+            line.text.chars().skip(indent).collect()
+        } else {
+            String::new()
+        }
+    });
+
+    first.into_iter().chain(rest).collect::<Vec<String>>().join("\n")
+}
+
+// A `${` of a string that has not been closed yet
+struct Interpolation {
+    // Braces opened inside it that are not closed yet, its own `}` is the one
+    // that comes when this is 0
+    depth: usize,
+    // Where the string's opening quote is
+    line: usize,
+    column: usize,
 }
 
 lazy_static! {
@@ -41,6 +112,11 @@ lazy_static! {
     };
 }
 
+/// All the reserved words of the language
+pub fn keywords() -> impl Iterator<Item = &'static str> {
+    HASHMAP.keys().copied()
+}
+
 impl Scanner {
     pub fn new(source: String) -> Self {
         Scanner {
@@ -49,26 +125,73 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
+            errors: Vec::new(),
+            trivia: Vec::new(),
+            interpolations: Vec::new(),
+        }
+    }
+
+    /// Scans `source` from the byte `offset` on, which has to be at the start
+    /// of line `line`. The REPL keeps the whole session as one source and
+    /// scans each input where it was appended, so positions point into it.
+    pub fn new_at(source: String, offset: usize, line: usize) -> Self {
+        Scanner {
+            start: offset,
+            current: offset,
+            line,
+            start_line: line,
+            ..Scanner::new(source)
         }
     }
 
+    /// Scans the whole source and reports every lexical error, the first one
+    /// is returned if there were any
     pub fn scan_tokens(&mut self) -> Result<Vec<Token>, LoxErrors> {
+        let (tokens, mut errors) = self.scan_all();
+
+        if errors.is_empty() {
+            return Ok(tokens);
+        }
+
+        errors.iter().for_each(scanner_error);
+        Err(errors.remove(0))
+    }
+
+    /// Scans the whole source without reporting anything, a bad character is
+    /// skipped so scanning carries on. Returns the tokens along with the errors.
+    pub fn scan_all(&mut self) -> (Vec<Token>, Vec<LoxErrors>) {
         while !self.is_at_end() {
             self.start = self.current;
-            self.scan_token()?;
+            self.start_line = self.line;
+            self.start_column = self.column;
+            let scanned = self.tokens.len();
+            if let Err(err) = self.scan_token() {
+                self.errors.push(err);
+            }
+
+            for token in &self.tokens[scanned..] {
+                trace!(SCANNER, "token", kind = token.token_type, lexeme = token.lexeme, line = token.line);
+            }
         }
 
-        self.tokens.push(Token::new(
-            TokenType::EOF,
-            "".to_string(),
-            LiteralValue::Null,
-            self.line,
-        ));
-        Ok(self.tokens.clone())
+        // Only the outermost string is reported, the others are inside it
+        if let Some(interpolation) = self.interpolations.first() {
+            self.errors.push(LoxErrors::UNTERMINATEDSTRING(interpolation.line, interpolation.column));
+            self.interpolations.clear();
+        }
+
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column;
+        self.push_token(TokenType::EOF, "".to_string(), LiteralValue::Null);
+        (std::mem::take(&mut self.tokens), std::mem::take(&mut self.errors))
     }
 
     pub fn is_at_end(&self) -> bool {
-        return self.current >= self.source.len() as u16;
+        self.current >= self.source.len()
     }
 
     pub fn scan_token(&mut self) -> Result<(), LoxErrors> {
@@ -76,8 +199,24 @@ impl Scanner {
         match character {
             '(' => self.add_token(TokenType::LEFT_PAREN),
             ')' => self.add_token(TokenType::RIGHT_PAREN),
-            '{' => self.add_token(TokenType::LEFT_BRACE),
-            '}' => self.add_token(TokenType::RIGHT_BRACE),
+            '{' => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.depth += 1;
+                }
+                self.add_token(TokenType::LEFT_BRACE)
+            }
+            '}' => match self.interpolations.last_mut() {
+                // Closes the `${`, the string carries on after it
+                Some(interpolation) if interpolation.depth == 0 => {
+                    let interpolation = self.interpolations.pop().unwrap();
+                    self.string_part(interpolation.line, interpolation.column, true)?
+                }
+                Some(interpolation) => {
+                    interpolation.depth -= 1;
+                    self.add_token(TokenType::RIGHT_BRACE)
+                }
+                None => self.add_token(TokenType::RIGHT_BRACE),
+            },
             ',' => self.add_token(TokenType::COMMA),
             '.' => self.add_token(TokenType::DOT),
             '-' => self.add_token(TokenType::MINUS),
@@ -85,7 +224,6 @@ impl Scanner {
             ';' => self.add_token(TokenType::SEMICOLON),
             '*' => self.add_token(TokenType::STAR),
             '!' => {
-                // println!("Current char: {}, {}", character, self.current);
                 if self.match_next('=') {
                     self.add_token(TokenType::BANG_EQUAL)
                 } else {
@@ -115,27 +253,50 @@ impl Scanner {
             }
             '/' => {
                 if self.match_next('/') {
+                    // Exactly three slashes make a doc comment, four or more
+                    // are an ordinary comment like in Rust
+                    let doc = self.peek() == '/' && self.peek_next() != '/';
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    if doc {
+                        self.doc_comment();
+                    }
+                } else if self.match_next('*') {
+                    self.block_comment()?;
                 } else {
                     self.add_token(TokenType::SLASH);
                 }
             }
             ' ' | '\r' | '\t' => {}
-            '\n' => {
-                self.line = self.line + 1;
+            '\n' => self.new_line(),
+            '"' => {
+                if self.match_str("\"\"") {
+                    self.multiline_string(false)?
+                } else {
+                    self.string()?
+                }
+            }
+            'r' if self.match_next('"') => {
+                if self.match_str("\"\"") {
+                    self.multiline_string(true)?
+                } else {
+                    self.raw_string()?
+                }
             }
-            '"' => self.string()?,
             _ => {
                 if self.is_digit(character) {
                     // CASE FOR NUMBERS
-                    return self.number();
+                    return self.number(character);
                 } else if self.is_alpha(character) {
                     // CASE FOR SPECIAL CHARACTERS
                     return self.identifier();
                 }
-                return Err(LoxErrors::INVALIDCHARCTER(character.to_string()));
+                return Err(LoxErrors::INVALIDCHARCTER(
+                    character.to_string(),
+                    self.start_line,
+                    self.start_column,
+                ));
             }
         }
 
@@ -151,7 +312,7 @@ impl Scanner {
         // See if the current value is some type of special character and if it
         // is then add that specific token and if it is not then it is an identifier
         // Just add it.
-        let value = self.substring(&self.source, self.start.into(), self.current.into())?;
+        let value = self.substring(&self.source, self.start, self.current)?;
         let special_type = HASHMAP.get(value.as_str());
 
         if let Some(val) = special_type {
@@ -170,12 +331,12 @@ impl Scanner {
             return true;
         }
 
-        return false;
+        false
     }
 
     fn is_alpha(&self, character: char) -> bool {
-        if (character >= 'A' && character <= 'Z')
-            || (character >= 'a' && character <= 'z')
+        if character.is_ascii_uppercase()
+            || character.is_ascii_lowercase()
             || character == '_'
         {
             return true;
@@ -188,110 +349,358 @@ impl Scanner {
         self.is_alpha(character) || self.is_digit(character)
     }
 
-    // Function to process numbers
-    fn number(&mut self) -> Result<(), LoxErrors> {
-        // Peek and see if the next char in the lexeme is a digit or not
-        while self.is_digit(self.peek()) {
-            self.advance();
+    // Function to process numbers, the first digit has been consumed. A
+    // malformed number still becomes a NUMBER token so the parser does not
+    // report a second error where it is.
+    fn number(&mut self, first: char) -> Result<(), LoxErrors> {
+        let value = self.number_value(first);
+        self.add_token_number(*value.as_ref().unwrap_or(&0.0));
+        value.map(|_| ())
+    }
+
+    // Besides decimals like `1_000.5e-3` there are `0x1F` and `0b1010` integers
+    fn number_value(&mut self, first: char) -> Result<f64, LoxErrors> {
+        if first == '0' {
+            match self.peek() {
+                'x' | 'X' => return self.radix_number(16, "hexadecimal"),
+                'b' | 'B' => return self.radix_number(2, "binary"),
+                _ => {}
+            }
         }
 
+        self.digits();
+
         // For decimals
         if self.peek() == '.' && self.is_digit(self.peek_next()) {
             self.advance();
+            self.digits();
+        }
 
-            while self.is_digit(self.peek()) {
+        if matches!(self.peek(), 'e' | 'E') {
+            self.advance();
+            if matches!(self.peek(), '+' | '-') {
                 self.advance();
             }
+            if !self.is_digit(self.peek()) {
+                self.digits();
+                return Err(self.malformed_number("an exponent needs digits after the 'e'"));
+            }
+            self.digits();
+        }
+
+        let text = self.substring(&self.source, self.start, self.current)?;
+        self.check_separators(&text, 10)?;
+
+        text.replace('_', "")
+            .parse::<f64>()
+            .map_err(|err| self.malformed_number(&err.to_string()))
+    }
+
+    // An integer after a `0x` or `0b` prefix, `name` is only used for the errors
+    fn radix_number(&mut self, radix: u32, name: &str) -> Result<f64, LoxErrors> {
+        self.advance();
+
+        // Letters are taken too so `0x1G` is one bad number instead of `0x1` and `G`
+        while self.is_alphanumic(self.peek()) {
+            self.advance();
         }
 
-        // Add the token
-        self.add_token_number(
-            TokenType::NUMBER,
-            self.substring(&self.source, self.start.into(), self.current.into())?,
-        )?;
+        let text = self.substring(&self.source, self.start, self.current)?;
+        let digits = &text[2..];
+        if digits.is_empty() {
+            return Err(self.malformed_number(&format!("{} numbers need digits after '{}'", name, &text[..2])));
+        }
+        if let Some(bad) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+            return Err(self.malformed_number(&format!("'{}' is not a {} digit", bad, name)));
+        }
+        self.check_separators(digits, radix)?;
 
+        let value = u64::from_str_radix(&digits.replace('_', ""), radix)
+            .map_err(|_| self.malformed_number("it does not fit in 64 bits"))?;
+        Ok(value as f64)
+    }
+
+    // Digits and the `_` separators between them
+    fn digits(&mut self) {
+        while self.is_digit(self.peek()) || self.peek() == '_' {
+            self.advance();
+        }
+    }
+
+    // A `_` has to have a digit on both sides
+    fn check_separators(&self, text: &str, radix: u32) -> Result<(), LoxErrors> {
+        let chars: Vec<char> = text.chars().collect();
+        for (index, c) in chars.iter().enumerate() {
+            let between_digits = index > 0
+                && chars[index - 1].is_digit(radix)
+                && chars.get(index + 1).is_some_and(|next| next.is_digit(radix));
+            if *c == '_' && !between_digits {
+                return Err(self.malformed_number("'_' can only go between digits"));
+            }
+        }
         Ok(())
     }
 
+    fn malformed_number(&self, reason: &str) -> LoxErrors {
+        LoxErrors::MALFORMEDNUMBER(
+            self.source[self.start..self.current].to_string(),
+            reason.to_string(),
+            self.start_line,
+            self.start_column,
+        )
+    }
+
     // Peek the current character
     fn peek(&self) -> char {
         if self.is_at_end() {
             return '\0';
         }
-        // SAFETY: Safe to unwrap
-        self.source.chars().nth(self.current as usize).unwrap()
+        self.char_at(self.current)
     }
 
     // Peek the next character
     pub fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() as u16 {
-            return '\0';
-        }
-        // SAFETY: Safe to unwrap
-        self.source
-            .chars()
-            .nth((self.current + 1) as usize)
-            .unwrap()
+        let mut chars = self.source[self.current..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
     pub fn match_next(&mut self, expected_char: char) -> bool {
-        if self.is_at_end() {
+        if self.is_at_end() || self.peek() != expected_char {
             return false;
         }
 
-        // SAFETY: Safe to unwrap() we are not at the end
-        println!(
-            "inside next match: {}",
-            &self.source.chars().nth((self.current).into()).unwrap()
-        );
-        if &self.source.chars().nth((self.current).into()).unwrap() == &expected_char {
-            self.current = self.current + 1;
-            return true;
-        }
-
-        false
+        self.advance();
+        true
     }
 
     /// Used for advancing character in a lexeme
     /// We consume the current char and return it, then shift to the next char
     pub fn advance(&mut self) -> char {
-        // SAFETY: Safe to unwrap here as we check for EOF inside is_at_end()
-        let res = self.source.chars().nth((self.current).into()).unwrap();
-        self.current = self.current + 1;
+        let res = self.char_at(self.current);
+        // Characters outside ASCII take more than one byte
+        self.current += res.len_utf8();
+        self.column += 1;
         res
     }
 
+    // Decodes the character starting at the byte `index`, which only looks at
+    // that one character so scanning stays linear in the size of the source
+    fn char_at(&self, index: usize) -> char {
+        self.source[index..].chars().next().unwrap_or('\0')
+    }
+
     pub fn string(&mut self) -> Result<(), LoxErrors> {
+        self.string_part(self.start_line, self.start_column, false)
+    }
+
+    // Scans up to the closing quote or the next `${`, whichever comes first.
+    // `line` and `column` are where the opening quote is, `continued` is for
+    // the parts that start with the `}` of an interpolation.
+    fn string_part(&mut self, line: usize, column: usize, continued: bool) -> Result<(), LoxErrors> {
+        // The value with the escapes decoded, the lexeme stays as written
+        let mut value = String::new();
+
         // Ending the string val
         while self.peek() != '"' && !self.is_at_end() {
-            // For new line, just modify our current line to be line + 1
-            if self.peek() == '\n' {
-                self.line = self.line + 1;
+            if self.peek() == '$' && self.peek_next() == '{' {
+                self.advance();
+                self.advance();
+                self.interpolations.push(Interpolation { depth: 0, line, column });
+
+                let token_type = if continued {
+                    TokenType::INTERPOLATION_MIDDLE
+                } else {
+                    TokenType::INTERPOLATION_START
+                };
+                return self.add_token_string(token_type, value);
+            }
+
+            let (line, column) = (self.line, self.column);
+            match self.advance() {
+                // For new line, just modify our current line to be line + 1
+                '\n' => {
+                    self.new_line();
+                    value.push('\n');
+                }
+                // A bad escape is left out so the rest of the string still scans
+                '\\' => match self.escape(line, column) {
+                    Ok(character) => value.push(character),
+                    Err(err) => self.errors.push(err),
+                },
+                character => value.push(character),
             }
-            self.advance();
         }
 
         // There could be a case where the left '"' is not specified -
         if self.is_at_end() {
-            return Err(LoxErrors::UNTERMINATEDSTRING());
+            // Strings can span lines, the error points at where it started
+            return Err(LoxErrors::UNTERMINATEDSTRING(line, column));
         }
 
         // Advancing if everything goes well!
         self.advance();
 
-        // Forming the string
-        let value = self
-            .substring(
-                &self.source,
-                (self.start + 1).into(),
-                (self.current - 1).into(),
-            )
-            .unwrap();
-        self.add_token_string(TokenType::STRING, value)?;
+        let token_type = if continued {
+            TokenType::INTERPOLATION_END
+        } else {
+            TokenType::STRING
+        };
+        self.add_token_string(token_type, value)?;
+
+        Ok(())
+    }
+
+    // The character an escape stands for, the `\` at `line` and `column` has
+    // been consumed
+    fn escape(&mut self, line: usize, column: usize) -> Result<char, LoxErrors> {
+        let start = self.current - 1;
+        // Left for the string to report as unterminated
+        if self.is_at_end() {
+            return Ok('\\');
+        }
+
+        let character = match self.advance() {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '$' => Some('$'),
+            '0' => Some('\0'),
+            'u' => self.unicode_escape(),
+            '\n' => {
+                self.new_line();
+                None
+            }
+            _ => None,
+        };
+
+        character.ok_or_else(|| {
+            LoxErrors::INVALIDESCAPE(self.source[start..self.current].trim_end().to_string(), line, column)
+        })
+    }
+
+    // The `{1F600}` part of a `\u{1F600}` escape
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.match_next('{') {
+            return None;
+        }
+
+        let start = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let end = self.current;
+
+        if !self.match_next('}') || end == start || end - start > 6 {
+            return None;
+        }
+        u32::from_str_radix(&self.source[start..end], 16).ok().and_then(char::from_u32)
+    }
+
+    // Block comments nest, so every `/*` needs its own `*/`
+    fn block_comment(&mut self) -> Result<(), LoxErrors> {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                return Err(LoxErrors::UNTERMINATEDCOMMENT(self.start_line, self.start_column));
+            }
+
+            match self.advance() {
+                '\n' => self.new_line(),
+                '/' if self.match_next('*') => depth += 1,
+                '*' if self.match_next('/') => depth -= 1,
+                _ => {}
+            }
+        }
 
         Ok(())
     }
 
+    // Kept aside until the next token is pushed, which it then belongs to. The
+    // literal is the text after the `///` without the space that usually follows.
+    fn doc_comment(&mut self) {
+        let lexeme = &self.source[self.start..self.current];
+        let text = lexeme["///".len()..].strip_prefix(' ').unwrap_or(&lexeme["///".len()..]);
+        let token = Token::new(
+            TokenType::DOC_COMMENT,
+            lexeme.to_string(),
+            LiteralValue::String(text.to_string()),
+            self.start_line,
+            self.start_column,
+            Span::new(self.start, self.current),
+        );
+        self.trivia.push(token);
+    }
+
+    // `r"..."`, everything up to the next quote is taken as it is
+    fn raw_string(&mut self) -> Result<(), LoxErrors> {
+        let mut value = String::new();
+        while self.peek() != '"' && !self.is_at_end() {
+            let character = self.advance();
+            if character == '\n' {
+                self.new_line();
+            }
+            value.push(character);
+        }
+
+        if self.is_at_end() {
+            return Err(LoxErrors::UNTERMINATEDSTRING(self.start_line, self.start_column));
+        }
+        self.advance();
+
+        self.add_token_string(TokenType::STRING, value)
+    }
+
+    // `"""..."""`, the opening quotes have been consumed. Escapes work like in
+    // other strings unless it is `raw`, there are no interpolations though.
+    // The indentation all the lines share is stripped, see `dedent`.
+    fn multiline_string(&mut self, raw: bool) -> Result<(), LoxErrors> {
+        let mut lines = Vec::new();
+        let mut current = StringLine::default();
+        while !self.match_str("\"\"\"") {
+            if self.is_at_end() {
+                return Err(LoxErrors::UNTERMINATEDSTRING(self.start_line, self.start_column));
+            }
+
+            let (line, column) = (self.line, self.column);
+            match self.advance() {
+                '\n' => {
+                    self.new_line();
+                    lines.push(std::mem::take(&mut current));
+                }
+                '\r' if self.peek() == '\n' => {}
+                '\\' if !raw => match self.escape(line, column) {
+                    Ok(character) => current.push(character, false),
+                    Err(err) => self.errors.push(err),
+                },
+                character => current.push(character, true),
+            }
+        }
+        lines.push(current);
+
+        self.add_token_string(TokenType::STRING, dedent(lines))
+    }
+
+    // Consumes `text` if the source carries on with it
+    fn match_str(&mut self, text: &str) -> bool {
+        if !self.source[self.current..].starts_with(text) {
+            return false;
+        }
+
+        text.chars().for_each(|_| {
+            self.advance();
+        });
+        true
+    }
+
+    // Called once the '\n' has been consumed
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.column = 1;
+    }
+
     pub fn add_token(&mut self, token_type: TokenType) {
         self.add_token_priv(token_type, "nil".to_string());
     }
@@ -303,38 +712,18 @@ impl Scanner {
     ) -> Result<(), LoxErrors> {
         // TODO: Find a way for this
         let text = self
-            .substring(&self.source, self.start.into(), self.current.into())
+            .substring(&self.source, self.start, self.current)
             .unwrap();
 
-        self.tokens.push(Token::new(
-            token_type,
-            text.to_string(),
-            LiteralValue::String(literal),
-            self.line,
-        ));
+        self.push_token(token_type, text.to_string(), LiteralValue::String(literal));
 
         Ok(())
     }
 
-    // Add a token for number
-    pub fn add_token_number(
-        &mut self,
-        token_type: TokenType,
-        literal: String,
-    ) -> Result<(), LoxErrors> {
-        // TODO: Find a way for this
-        let text = self
-            .substring(&self.source, self.start.into(), self.current.into())
-            .unwrap().parse::<f64>().unwrap();
-
-        self.tokens.push(Token::new(
-            token_type,
-            text.to_string(),
-            LiteralValue::Number(text),
-            self.line,
-        ));
-
-        Ok(())
+    // Add a token for number, the lexeme keeps the number as it was written
+    pub fn add_token_number(&mut self, value: f64) {
+        let text = self.source[self.start..self.current].to_string();
+        self.push_token(TokenType::NUMBER, text, LiteralValue::Number(value));
     }
 
     // TODO: Move this to another struct (LoxCommon)
@@ -346,12 +735,323 @@ impl Scanner {
         start_index: usize,
         end_index: usize,
     ) -> Result<String, LoxErrors> {
-        Ok(string_to_work[(start_index) as usize..(end_index) as usize].to_string())
+        Ok(string_to_work[start_index..end_index].to_string())
     }
 
     pub fn add_token_priv(&mut self, token_type: TokenType, literal: String) {
-        let text = &self.source.as_str()[self.start as usize..self.current as usize];
-        self.tokens
-            .push(Token::new(token_type, text.to_string(), LiteralValue::String(literal), self.line));
+        let text = &self.source.as_str()[self.start..self.current];
+        self.push_token(token_type, text.to_string(), LiteralValue::String(literal));
+    }
+
+    // Every token covers the source from the start of the lexeme up to the
+    // current character, and takes the doc comments scanned since the last one
+    fn push_token(&mut self, token_type: TokenType, lexeme: String, literal: LiteralValue) {
+        let span = Span::new(self.start, self.current);
+        let mut token = Token::new(
+            token_type,
+            lexeme,
+            literal,
+            self.start_line,
+            self.start_column,
+            span,
+        );
+        token.set_trivia(std::mem::take(&mut self.trivia));
+        self.tokens.push(token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scanner;
+    use crate::{error::LoxErrors, literal::LiteralValue, parser::Parser, token_type::TokenType};
+
+    #[test]
+    fn test_scan_tokens() {
+        let tokens = Scanner::new("var a = \"hi\";\nprint a >= 1.5;".to_string())
+            .scan_tokens()
+            .unwrap();
+        let token_types: Vec<TokenType> = tokens.iter().map(|token| token.token_type.clone()).collect();
+
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::VAR,
+                TokenType::IDENTIFIER,
+                TokenType::EQUAL,
+                TokenType::STRING,
+                TokenType::SEMICOLON,
+                TokenType::PRINT,
+                TokenType::IDENTIFIER,
+                TokenType::GREATER_EQUAL,
+                TokenType::NUMBER,
+                TokenType::SEMICOLON,
+                TokenType::EOF,
+            ]
+        );
+        assert_eq!(tokens[5].line, 2);
+    }
+
+    #[test]
+    fn test_token_positions() {
+        let tokens = Scanner::new("var a = \"two\nlines\";\n  a;".to_string())
+            .scan_tokens()
+            .unwrap();
+
+        let positions: Vec<(usize, usize, usize, usize)> = tokens
+            .iter()
+            .map(|token| (token.line, token.column, token.span.start, token.span.end))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (1, 1, 0, 3),
+                (1, 5, 4, 5),
+                (1, 7, 6, 7),
+                // A string that spans lines starts where its opening quote is
+                (1, 9, 8, 19),
+                (2, 7, 19, 20),
+                (3, 3, 23, 24),
+                (3, 4, 24, 25),
+                (3, 5, 25, 25),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keeps_scanning_after_errors() {
+        let (tokens, errors) = Scanner::new("a @ b\n  # c".to_string()).scan_all();
+
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(lexemes, vec!["a", "b", "c", ""]);
+        assert!(matches!(
+            &errors[..],
+            [LoxErrors::INVALIDCHARCTER(a, 1, 3), LoxErrors::INVALIDCHARCTER(b, 2, 3)] if a == "@" && b == "#"
+        ));
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let (tokens, errors) = Scanner::new("a /* one /* two\n */ still\n */ b / c".to_string()).scan_all();
+
+        let lexemes: Vec<(&str, usize)> = tokens.iter().map(|token| (token.lexeme.as_str(), token.line)).collect();
+        assert_eq!(lexemes, vec![("a", 1), ("b", 3), ("/", 3), ("c", 3), ("", 3)]);
+        assert!(errors.is_empty());
+
+        let (_, errors) = Scanner::new("a;\n  /* /* */\n".to_string()).scan_all();
+        assert!(matches!(errors[..], [LoxErrors::UNTERMINATEDCOMMENT(2, 3)]));
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let source = "/// Adds them.\n///Twice.\n//// Not docs.\nfun add() {}\n/// At the end";
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+
+        let docs: Vec<(&str, usize)> = tokens[0]
+            .trivia()
+            .iter()
+            .map(|doc| match &doc.literal {
+                LiteralValue::String(text) => (text.as_str(), doc.line),
+                other => panic!("Expected the text of the comment, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(docs, vec![("Adds them.", 1), ("Twice.", 2)]);
+        assert_eq!(tokens[0].token_type, TokenType::FUN);
+        assert!(tokens[1..tokens.len() - 1].iter().all(|token| token.trivia().is_empty()));
+
+        // Doc comments are never tokens of their own
+        let eof = tokens.last().unwrap();
+        assert_eq!((eof.token_type.clone(), eof.trivia().len()), (TokenType::EOF, 1));
+        assert!(tokens.iter().all(|token| token.token_type != TokenType::DOC_COMMENT));
+    }
+
+    #[test]
+    fn test_escapes() {
+        let source = r#""a\n\t\r\\ \"b\" \0 \u{1F600}\u{e9}""#;
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+
+        assert_eq!(tokens[0].lexeme, source);
+        assert!(matches!(&tokens[0].literal, LiteralValue::String(value) if value == "a\n\t\r\\ \"b\" \0 😀é"));
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        let source = r#"print "\q \u{110000} \u{} \u12";"#;
+        let (tokens, errors) = Scanner::new(source.to_string()).scan_all();
+
+        let escapes: Vec<(&str, usize)> = errors
+            .iter()
+            .map(|err| match err {
+                LoxErrors::INVALIDESCAPE(escape, 1, column) => (escape.as_str(), *column),
+                other => panic!("Expected an invalid escape, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(escapes, vec![("\\q", 8), ("\\u{110000}", 11), ("\\u{}", 22), ("\\u", 27)]);
+
+        // The string still ends at its closing quote
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(lexemes, vec!["print", source[6..source.len() - 1].as_ref(), ";", ""]);
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let source = r#"print "a ${ "b${c}" } {d} ${e}";"#;
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+
+        let pieces: Vec<(TokenType, &str)> = tokens
+            .iter()
+            .map(|token| (token.token_type.clone(), token.lexeme.as_str()))
+            .collect();
+        assert_eq!(
+            pieces,
+            vec![
+                (TokenType::PRINT, "print"),
+                (TokenType::INTERPOLATION_START, "\"a ${"),
+                (TokenType::INTERPOLATION_START, "\"b${"),
+                (TokenType::IDENTIFIER, "c"),
+                (TokenType::INTERPOLATION_END, "}\""),
+                (TokenType::INTERPOLATION_MIDDLE, "} {d} ${"),
+                (TokenType::IDENTIFIER, "e"),
+                (TokenType::INTERPOLATION_END, "}\""),
+                (TokenType::SEMICOLON, ";"),
+                (TokenType::EOF, ""),
+            ]
+        );
+        assert!(matches!(&tokens[5].literal, LiteralValue::String(value) if value == " {d} "));
+
+        let (_, errors) = Scanner::new("a;\n  \"x ${ y".to_string()).scan_all();
+        assert!(matches!(errors[..], [LoxErrors::UNTERMINATEDSTRING(2, 3)]));
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let source = r#"r"C:\new\${x}" r"two
+lines" r"""
+  "quoted" \d+
+"""; a"#;
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+
+        let strings: Vec<(&str, usize)> = tokens
+            .iter()
+            .filter_map(|token| match &token.literal {
+                LiteralValue::String(value) if token.token_type == TokenType::STRING => Some((value.as_str(), token.line)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(strings, vec![(r"C:\new\${x}", 1), ("two\nlines", 1), (r#""quoted" \d+"#, 2)]);
+        assert_eq!(tokens[0].lexeme, r#"r"C:\new\${x}""#);
+        assert_eq!((tokens[4].lexeme.as_str(), tokens[4].line), ("a", 4));
+
+        let (_, errors) = Scanner::new("a;\n r\"x".to_string()).scan_all();
+        assert!(matches!(errors[..], [LoxErrors::UNTERMINATEDSTRING(2, 2)]));
+    }
+
+    #[test]
+    fn test_multiline_strings() {
+        let value = |source: &str| match Scanner::new(source.to_string()).scan_tokens().unwrap().remove(0).literal {
+            LiteralValue::String(value) => value,
+            other => panic!("Expected a string, got {:?}", other),
+        };
+
+        assert_eq!(
+            value("\"\"\"\n    SELECT *\n      FROM t\n\n    WHERE a = \\\"${b}\\\"\\n\n    \"\"\""),
+            "SELECT *\n  FROM t\n\nWHERE a = \"${b}\"\n"
+        );
+        assert_eq!(value("\"\"\"one \"two\" \\t\"\"\""), "one \"two\" \t");
+        assert_eq!(value("\"\"\"first\n  \\tsecond\n   third\"\"\""), "first\n\tsecond\n third");
+        assert_eq!(value("\"\"\"\"\"\""), "");
+
+        let (_, errors) = Scanner::new("\"\"\"\nnever closed\"\"".to_string()).scan_all();
+        assert!(matches!(errors[..], [LoxErrors::UNTERMINATEDSTRING(1, 1)]));
+    }
+
+    #[test]
+    fn test_numbers() {
+        let (tokens, errors) = Scanner::new("0x1F 0b1010 1e-9 2.5E10 1_000_000 0XfF_00 3.25".to_string()).scan_all();
+
+        let numbers: Vec<(&str, f64)> = tokens
+            .iter()
+            .filter_map(|token| match token.literal {
+                LiteralValue::Number(value) => Some((token.lexeme.as_str(), value)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            numbers,
+            vec![
+                ("0x1F", 31.0),
+                ("0b1010", 10.0),
+                ("1e-9", 1e-9),
+                ("2.5E10", 2.5e10),
+                ("1_000_000", 1_000_000.0),
+                ("0XfF_00", 65280.0),
+                ("3.25", 3.25)
+            ]
+        );
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_numbers() {
+        let source = "0x; 1e; 0b102; 1__0; 2_; 1_e5; 1e+; 0x1_0000_0000_0000_0000; 1.x";
+        let (tokens, errors) = Scanner::new(source.to_string()).scan_all();
+
+        let malformed: Vec<(&str, usize)> = errors
+            .iter()
+            .map(|err| match err {
+                LoxErrors::MALFORMEDNUMBER(number, _, _, column) => (number.as_str(), *column),
+                other => panic!("Expected a malformed number, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            malformed,
+            vec![
+                ("0x", 1),
+                ("1e", 5),
+                ("0b102", 9),
+                ("1__0", 16),
+                ("2_", 22),
+                ("1_e5", 26),
+                ("1e+", 32),
+                ("0x1_0000_0000_0000_0000", 37),
+            ]
+        );
+
+        // A dot without digits after it is still a method call or property
+        let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert!(lexemes.ends_with(&["1", ".", "x", ""]));
+
+        // Malformed ones are still numbers to the parser, which has nothing
+        // more to report
+        assert_eq!(tokens[0].token_type, TokenType::NUMBER);
+        assert_eq!(tokens[0].lexeme, "0x");
+        for source in ["print 0x;", "print 1.5e;", "print 0b12 + 1;"] {
+            let (tokens, errors) = Scanner::new(source.to_string()).scan_all();
+            assert_eq!(errors.len(), 1);
+            let (_, errors) = Parser::new(tokens).parse_all();
+            assert!(errors.is_empty(), "{}: {:?}", source, errors);
+        }
+    }
+
+    #[test]
+    fn test_unicode() {
+        let source = "// ünïcödé comment\nprint \"héllo 🌍\" + a;\n€";
+        let (tokens, errors) = Scanner::new(source.to_string()).scan_all();
+
+        let string = &tokens[1];
+        assert_eq!(string.lexeme, "\"héllo 🌍\"");
+        assert!(matches!(&string.literal, LiteralValue::String(value) if value == "héllo 🌍"));
+        assert_eq!(&source[string.span.start..string.span.end], string.lexeme);
+
+        // Columns count characters, not bytes
+        let plus = &tokens[2];
+        assert_eq!((plus.lexeme.as_str(), plus.line, plus.column), ("+", 2, 17));
+
+        assert!(matches!(&errors[..], [LoxErrors::INVALIDCHARCTER(c, 3, 1)] if c == "€"));
+    }
+
+    #[test]
+    fn test_unterminated_string_reports_its_start() {
+        let (_, errors) = Scanner::new("print 1;\n  print \"never\nclosed\n".to_string()).scan_all();
+        assert!(matches!(errors[..], [LoxErrors::UNTERMINATEDSTRING(2, 9)]));
     }
 }
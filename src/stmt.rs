@@ -0,0 +1,251 @@
+use std::rc::Rc;
+
+use crate::{
+    expr::{AstPrinter, Expr},
+    token::{Span, Token},
+};
+
+/*
+* program        → declaration* EOF ;
+  declaration    → classDecl
+                   | funDecl
+                   | varDecl
+                   | statement ;
+  classDecl      → "class" IDENTIFIER ( "<" IDENTIFIER )?
+                   "{" function* "}" ;
+  funDecl        → "fun" function ;
+  function       → IDENTIFIER "(" parameters? ")" block ;
+  parameters     → IDENTIFIER ( "," IDENTIFIER )* ;
+  varDecl        → "var" IDENTIFIER ( "=" expression )? ";" ;
+  statement      → exprStmt
+                   | ifStmt
+                   | printStmt
+                   | returnStmt
+                   | whileStmt
+                   | block ;
+  exprStmt       → expression ";" ;
+  ifStmt         → "if" "(" expression ")" statement
+                   ( "else" statement )? ;
+  printStmt      → "print" expression ";" ;
+  returnStmt     → "return" expression? ";" ;
+  whileStmt      → "while" "(" expression ")" statement ;
+  block          → "{" declaration* "}" ;
+*/
+
+// Every statement keeps the span of source it was parsed from, from its first
+// token to its last
+#[derive(Debug)]
+pub enum Stmt {
+    Expression {
+        expression: Expr,
+        span: Span,
+    },
+    Print {
+        expression: Expr,
+        span: Span,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Expr>,
+        // The `DOC_COMMENT` tokens right before the declaration
+        docs: Vec<Token>,
+        span: Span,
+    },
+    Block {
+        statements: Vec<Stmt>,
+        span: Span,
+    },
+    If {
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+        span: Span,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+        span: Span,
+    },
+    Function {
+        name: Token,
+        params: Vec<Token>,
+        // Shared with every function value created from this declaration
+        body: Rc<Vec<Stmt>>,
+        docs: Vec<Token>,
+        span: Span,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
+        span: Span,
+    },
+    Class {
+        name: Token,
+        // Always an `Expr::Variable`
+        superclass: Option<Expr>,
+        // Always `Stmt::Function`s
+        methods: Vec<Stmt>,
+        docs: Vec<Token>,
+        span: Span,
+    },
+}
+
+// Visitor pattern implementation
+pub trait Visitor<R> {
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> R;
+    fn visit_print_stmt(&mut self, expression: &Expr) -> R;
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> R;
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> R;
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> R;
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> R;
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Token], body: &Rc<Vec<Stmt>>) -> R;
+    fn visit_return_stmt(&mut self, keyword: &Token, value: Option<&Expr>) -> R;
+    fn visit_class_stmt(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) -> R;
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Var { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Class { span, .. } => *span,
+        }
+    }
+
+    /// The doc comments of a declaration, other statements have none
+    pub fn docs(&self) -> &[Token] {
+        match self {
+            Stmt::Var { docs, .. } | Stmt::Function { docs, .. } | Stmt::Class { docs, .. } => docs,
+            _ => &[],
+        }
+    }
+
+    pub fn accept<R>(&self, visitor: &mut dyn Visitor<R>) -> R {
+        match self {
+            Stmt::Expression { expression, .. } => visitor.visit_expression_stmt(expression),
+            Stmt::Print { expression, .. } => visitor.visit_print_stmt(expression),
+            Stmt::Var { name, initializer, .. } => visitor.visit_var_stmt(name, initializer.as_ref()),
+            Stmt::Block { statements, .. } => visitor.visit_block_stmt(statements),
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                visitor.visit_if_stmt(condition, then_branch, else_branch.as_deref())
+            }
+            Stmt::While { condition, body, .. } => visitor.visit_while_stmt(condition, body),
+            Stmt::Function { name, params, body, .. } => visitor.visit_function_stmt(name, params, body),
+            Stmt::Return { keyword, value, .. } => visitor.visit_return_stmt(keyword, value.as_ref()),
+            Stmt::Class { name, superclass, methods, .. } => {
+                visitor.visit_class_stmt(name, superclass.as_ref(), methods)
+            }
+        }
+    }
+}
+
+impl AstPrinter {
+    /// Prints a statement in the same prefix notation as expressions
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    fn print_block(&mut self, name: String, statements: &[Stmt]) -> String {
+        let mut r = String::new();
+        r.push('(');
+        r.push_str(&name);
+        for statement in statements {
+            r.push(' ');
+            r.push_str(&statement.accept(self));
+        }
+        r.push(')');
+        r
+    }
+}
+
+impl Visitor<String> for AstPrinter {
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> String {
+        self.parenthesize(";".to_string(), vec![expression])
+    }
+
+    fn visit_print_stmt(&mut self, expression: &Expr) -> String {
+        self.parenthesize("print".to_string(), vec![expression])
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> String {
+        match initializer {
+            Some(initializer) => self.parenthesize(format!("var {} =", name.lexeme), vec![initializer]),
+            None => format!("(var {})", name.lexeme),
+        }
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> String {
+        self.print_block("block".to_string(), statements)
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> String {
+        let mut r = format!("(if {} {}", condition.accept(self), then_branch.accept(self));
+        if let Some(else_branch) = else_branch {
+            r.push(' ');
+            r.push_str(&else_branch.accept(self));
+        }
+        r.push(')');
+        r
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> String {
+        format!("(while {} {})", condition.accept(self), body.accept(self))
+    }
+
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Token], body: &Rc<Vec<Stmt>>) -> String {
+        let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_str()).collect();
+        self.print_block(format!("fun {}({})", name.lexeme, params.join(" ")), body)
+    }
+
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: Option<&Expr>) -> String {
+        match value {
+            Some(value) => self.parenthesize("return".to_string(), vec![value]),
+            None => "(return)".to_string(),
+        }
+    }
+
+    fn visit_class_stmt(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) -> String {
+        let name = match superclass {
+            Some(superclass) => format!("class {} < {}", name.lexeme, superclass.accept(self)),
+            None => format!("class {}", name.lexeme),
+        };
+        self.print_block(name, methods)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expr::AstPrinter, parser::Parser, scanner::Scanner};
+
+    fn print(source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().expect("Could not parse sample code.");
+        statements.iter().map(|statement| AstPrinter.print_stmt(statement)).collect()
+    }
+
+    #[test]
+    fn test_printer() {
+        assert_eq!(
+            print("var a = 1; if (a) print a; else { a = 2; }"),
+            vec!["(var a = 1)", "(if a (print a) (block (; (= a 2))))"]
+        );
+        assert_eq!(
+            print("class B < A { m(x, y) { return x; } }"),
+            vec!["(class B < A (fun m(x y) (return x)))"]
+        );
+        assert_eq!(
+            print(r#"print "a ${b} ${"\${"}\n${c + 1}";"#),
+            vec![r#"(print "a ${b} ${"\${"}\n${(+ c 1)}")"#]
+        );
+        // Interpolations print back the way they were written
+        assert_eq!(print(r#""a ${b} c";"#), vec![r#"(; "a ${b} c")"#]);
+        assert_eq!(print(r#""${"x ${y}"}${z}";"#), vec![r#"(; "${"x ${y}"}${z}")"#]);
+        assert_eq!(print(r#""a " + "${b}" + " c";"#), vec![r#"(; (+ (+ "a " "${b}") " c"))"#]);
+    }
+}
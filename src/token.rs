@@ -1,6 +1,26 @@
 // Tokens are lexemes only with a bit of more information
+use std::rc::Rc;
+
 use crate::{literal::LiteralValue, token_type::TokenType};
 
+// Byte range of a piece of the source, `end` is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The span from the start of this one to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
+
 // This will be the tokens
 #[derive(Debug, Clone)]
 pub struct Token {
@@ -8,25 +28,51 @@ pub struct Token {
     pub lexeme: String,
     // FIXME: We should use Option here
     pub literal: LiteralValue,
-    pub line: u16,
+    pub line: usize,
+    // Column of the first character, starting from 1
+    pub column: usize,
+    pub span: Span,
+    // The `///` doc comments right before this token, behind a pointer so
+    // tokens and the errors that carry them stay small when there are none
+    trivia: Option<Rc<Vec<Token>>>,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: LiteralValue, line: u16) -> Self {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        literal: LiteralValue,
+        line: usize,
+        column: usize,
+        span: Span,
+    ) -> Self {
         Self {
             token_type,
             lexeme,
             literal,
             line,
+            column,
+            span,
+            trivia: None,
         }
     }
+
+    /// The doc comments scanned right before this token
+    pub fn trivia(&self) -> &[Token] {
+        self.trivia.as_deref().map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set_trivia(&mut self, trivia: Vec<Token>) {
+        self.trivia = if trivia.is_empty() { None } else { Some(Rc::new(trivia)) };
+    }
 }
 
-impl ToString for Token {
-    fn to_string(&self) -> String {
-        format!(
-            "token_type: {}, lexeme: {}, literal: {}, line: {}",
-            self.token_type, self.lexeme, self.literal, self.line
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "token_type: {}, lexeme: {}, literal: {}, line: {}, column: {}, span: {}..{}",
+            self.token_type, self.lexeme, self.literal, self.line, self.column, self.span.start, self.span.end
         )
     }
 }
@@ -1,4 +1,4 @@
-// #[allow(dead_code, unused_variables)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenType {
   // Single-character tokens.
@@ -14,17 +14,24 @@ pub enum TokenType {
   // Literals.
   IDENTIFIER, STRING, NUMBER,
 
+  // Pieces of a string with `${}`s, `"a ${` then `} b ${` then `} c"`.
+  INTERPOLATION_START, INTERPOLATION_MIDDLE, INTERPOLATION_END,
+
   // Keywords.
   AND, CLASS, ELSE, FALSE, FUN, FOR, IF, NIL, OR,
   PRINT, RETURN, SUPER, THIS, TRUE, VAR, WHILE,
 
+  #[allow(dead_code)]
   NEW_LINE,
 
+  // Trivia, never in the token list itself but kept on the token after it
+  DOC_COMMENT,
+
   EOF
 }
 
 impl std::fmt::Display for TokenType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "TokenType: {}", self)
+        write!(f, "TokenType: {:?}", self)
     }
 }
\ No newline at end of file
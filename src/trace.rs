@@ -0,0 +1,86 @@
+use std::{
+    env, fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+// Bit set of the channels that are switched on, everything is off by default
+static ENABLED: AtomicU8 = AtomicU8::new(0);
+
+// Name of the environment variable holding a comma separated list of channels
+const LOG_VARIABLE: &str = "LOX_LOG";
+
+// The parts of the pipeline that can emit trace events
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    SCANNER = 1,
+    PARSER = 2,
+    EXEC = 4,
+}
+
+impl Channel {
+    const ALL: [Channel; 3] = [Channel::SCANNER, Channel::PARSER, Channel::EXEC];
+
+    fn name(self) -> &'static str {
+        match self {
+            Channel::SCANNER => "scanner",
+            Channel::PARSER => "parser",
+            Channel::EXEC => "exec",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Channel> {
+        Channel::ALL.into_iter().find(|channel| channel.name() == name)
+    }
+}
+
+pub fn enable(channel: Channel) {
+    ENABLED.fetch_or(channel as u8, Ordering::Relaxed);
+}
+
+pub fn is_enabled(channel: Channel) -> bool {
+    ENABLED.load(Ordering::Relaxed) & channel as u8 != 0
+}
+
+/// Switches on the channels listed in `LOX_LOG`, `all` switches on every one
+/// of them. Unknown names are skipped with a warning.
+pub fn enable_from_env() {
+    let Ok(value) = env::var(LOG_VARIABLE) else {
+        return;
+    };
+
+    for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        match name {
+            "all" => Channel::ALL.into_iter().for_each(enable),
+            _ => match Channel::from_name(name) {
+                Some(channel) => enable(channel),
+                None => eprintln!("Unknown {} channel '{}'.", LOG_VARIABLE, name),
+            },
+        }
+    }
+}
+
+/// Writes one event to stderr as `[trace channel] event key=value ...`
+pub fn emit(channel: Channel, event: &str, fields: &[(&str, &dyn fmt::Debug)]) {
+    let mut line = format!("[trace {}] {}", channel.name(), event);
+    for (key, value) in fields {
+        line.push_str(&format!(" {}={:?}", key, value));
+    }
+    eprintln!("{}", line);
+}
+
+// Emits an event when its channel is enabled, the fields are only evaluated
+// in that case so tracing costs nothing when it is off
+macro_rules! trace {
+    ($channel:ident, $event:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        if $crate::trace::is_enabled($crate::trace::Channel::$channel) {
+            $crate::trace::emit(
+                $crate::trace::Channel::$channel,
+                $event,
+                &[$((stringify!($key), &$value as &dyn std::fmt::Debug)),*],
+            );
+        }
+    };
+}
+
+pub(crate) use trace;